@@ -1,5 +1,5 @@
 // Black goes top -> bottom. White goes left -> right
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Tile {
     Black,
     Empty,
@@ -16,24 +16,254 @@ impl std::fmt::Display for Tile {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum DFS {
-    Visited,
-    Visiting,
-    Unvisited,
+impl Tile {
+    // The other player's color. Empty stays empty
+    pub fn opponent(self) -> Tile {
+        match self {
+            Self::Black => Self::White,
+            Self::White => Self::Black,
+            Self::Empty => Self::Empty,
+        }
+    }
+}
+
+// Largest board whose rows can all be named with a letter
+pub const MAX_SIZE: usize = 26;
+
+// A single cell on the board. Row is the letter, column is the number. Ex: "c4" is (2, 3)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Coord {
+    pub row: usize,
+    pub col: usize,
+}
+
+impl Coord {
+    pub fn new(row: usize, col: usize) -> Self {
+        Self { row, col }
+    }
+
+    // Parses a coordinate of the form `[a-z][0-9]+` on a board with the given side length
+    pub fn parse(mv: &str, size: usize) -> Result<Self, MoveError> {
+        let mut chars = mv.chars();
+        let letter = chars.next().ok_or(MoveError::Empty)?;
+
+        if !letter.is_ascii_lowercase() {
+            return Err(MoveError::BadLetter(letter));
+        }
+
+        let rest = chars.as_str();
+        let digits_end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let (digits, trailing) = rest.split_at(digits_end);
+
+        if digits.is_empty() {
+            return Err(MoveError::MissingNumber);
+        } else if !trailing.is_empty() {
+            return Err(MoveError::TrailingGarbage(trailing.to_string()));
+        }
+
+        let row = letter as usize - 'a' as usize;
+        if row >= size {
+            return Err(MoveError::RowOutOfRange(letter));
+        }
+
+        // Overflowing numbers are definitely off the board too
+        let col = digits.parse::<usize>().unwrap_or(usize::MAX);
+        if col == 0 || col > size {
+            return Err(MoveError::ColumnOutOfRange(digits.to_string()));
+        }
+
+        Ok(Self::new(row, col - 1))
+    }
+}
+
+impl std::fmt::Display for Coord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", (b'a' + self.row as u8) as char, self.col + 1)
+    }
+}
+
+// Anything a bot may reply with to `make_move`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Move {
+    Swap,
+    Place(Coord),
+}
+
+impl Move {
+    pub fn parse(mv: &str, size: usize) -> Result<Self, MoveError> {
+        if mv == "swap" {
+            Ok(Self::Swap)
+        } else {
+            Coord::parse(mv, size).map(Self::Place)
+        }
+    }
+}
+
+impl std::fmt::Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Swap => write!(f, "swap"),
+            Self::Place(coord) => write!(f, "{}", coord),
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveError {
+    Empty,
+    BadLetter(char),
+    MissingNumber,
+    TrailingGarbage(String),
+    RowOutOfRange(char),
+    ColumnOutOfRange(String),
+    Occupied(Coord),
+    MisplacedSwap,
+}
+
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty move"),
+            Self::BadLetter(c) => write!(f, "`{}` is not a lowercase row letter", c.escape_debug()),
+            Self::MissingNumber => write!(f, "missing column number after the row letter"),
+            Self::TrailingGarbage(s) => write!(f, "unexpected trailing characters `{}`", s.escape_debug()),
+            Self::RowOutOfRange(c) => write!(f, "row `{}` is off the board", c),
+            Self::ColumnOutOfRange(s) => write!(f, "column `{}` is off the board", s),
+            Self::Occupied(coord) => write!(f, "tile {} is already taken", coord),
+            Self::MisplacedSwap => write!(f, "swap is only allowed as the second move of the game"),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+// Ways of transforming a position into an equivalent one. Rotating the board by 180 degrees keeps
+// every edge with its owner. Transposing it hands black's edges to white and vice versa, so the
+// colors have to be swapped along with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    Identity,
+    Rotation,
+    ColorSwap,
+    RotatedColorSwap,
+}
+
+impl Symmetry {
+    pub const ALL: [Self; 4] = [Self::Identity, Self::Rotation, Self::ColorSwap, Self::RotatedColorSwap];
+
+    // Where a tile ends up on a board with the given side length
+    pub fn coord(self, coord: Coord, size: usize) -> Coord {
+        let rotated = |c: Coord| Coord::new(size - 1 - c.row, size - 1 - c.col);
+        let transposed = |c: Coord| Coord::new(c.col, c.row);
+
+        match self {
+            Self::Identity => coord,
+            Self::Rotation => rotated(coord),
+            Self::ColorSwap => transposed(coord),
+            Self::RotatedColorSwap => rotated(transposed(coord)),
+        }
+    }
+
+    pub fn tile(self, tile: Tile) -> Tile {
+        match self {
+            Self::ColorSwap | Self::RotatedColorSwap => tile.opponent(),
+            _ => tile,
+        }
+    }
+
+    pub fn apply(self, mv: Move, size: usize) -> Move {
+        match mv {
+            Move::Swap => Move::Swap,
+            Move::Place(coord) => Move::Place(self.coord(coord, size)),
+        }
+    }
+}
+
+// Virtual nodes stored after the last tile in the union-find forest. Black connects top to
+// bottom, white connects left to right
+const TOP: usize = 0;
+const BOTTOM: usize = 1;
+const LEFT: usize = 2;
+const RIGHT: usize = 3;
+const EDGE_COUNT: usize = 4;
+
+// Zobrist key of a stone, from a fixed mix of its index and color rather than a random table, so
+// that hashes are the same on every run and machine. Empty tiles don't change the hash
+fn zobrist_key(index: usize, tile: Tile) -> u64 {
+    let color = match tile {
+        Tile::Black => 1,
+        Tile::White => 2,
+        Tile::Empty => return 0,
+    };
+
+    // splitmix64 finalizer
+    let mut x = (index as u64 * 2 + color).wrapping_mul(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+// Disjoint set forest with path compression and union by size
+#[derive(Debug, Clone)]
+struct DisjointSet {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+            sizes: vec![1; len],
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+
+        if a == b {
+            return;
+        }
+        if self.sizes[a] < self.sizes[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parents[b] = a;
+        self.sizes[a] += self.sizes[b];
+    }
+
+    fn is_connected(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Board {
     size: usize,
     board: Vec<Tile>,
+    groups: DisjointSet,
+    winner: Tile,
+    // Xor of the Zobrist keys of every stone on the board
+    hash: u64,
 }
 
 impl Board {
     pub fn new(size: u8) -> Self {
+        let len = size as usize * size as usize;
+
         Self {
             size: size as usize,
-            board: vec![Tile::Empty; size.pow(2) as usize],
+            board: vec![Tile::Empty; len],
+            groups: DisjointSet::new(len + EDGE_COUNT),
+            winner: Tile::Empty,
+            hash: 0,
         }
     }
 
@@ -52,47 +282,346 @@ impl Board {
             })
             .collect::<Vec<Tile>>();
 
-        Self {
+        let mut new = Self {
             size: (board.len() as f64).sqrt() as usize,
+            groups: DisjointSet::new(board.len() + EDGE_COUNT),
+            hash: board.iter().enumerate().fold(0, |hash, (i, &tile)| hash ^ zobrist_key(i, tile)),
             board,
+            winner: Tile::Empty,
+        };
+        new.rebuild_groups();
+        new
+    }
+
+    // Like `from`, but returns an error for anything that isn't a square board instead of panicking
+    pub fn parse(compressed: &str) -> Result<Self, String> {
+        let rows: Vec<&str> = compressed.trim().trim_end_matches('|').split('|').collect();
+
+        if let Some(c) = compressed.trim().chars().find(|c| !"BW.|".contains(*c)) {
+            return Err(format!("`{}` isn't a tile", c));
+        }
+        if rows.len() > MAX_SIZE || rows.iter().any(|row| row.len() != rows.len()) {
+            return Err(format!("{} rows don't make a square board", rows.len()));
         }
+        Ok(Self::from(compressed))
+    }
+
+    // The format read by `Board::from`, as printed by bots for `show_board`. Ex: "...|B.B|.W.|"
+    pub fn to_compressed(&self) -> String {
+        let mut compressed = String::with_capacity(self.board.len() + self.size);
+
+        for row in self.board.chunks(self.size.max(1)) {
+            for tile in row {
+                compressed.push(match tile {
+                    Tile::Black => 'B',
+                    Tile::White => 'W',
+                    Tile::Empty => '.',
+                });
+            }
+            compressed.push('|');
+        }
+        compressed
+    }
+
+    // Tiles that hold something different on the other board, which must be the same size
+    pub fn diff(&self, other: &Board) -> Vec<Coord> {
+        (0..self.board.len())
+            .filter(|&i| self.board[i] != other.board[i])
+            .map(|i| Coord::new(i / self.size, i % self.size))
+            .collect()
+    }
+
+    // Side length of the board
+    pub fn size(&self) -> usize {
+        self.size
     }
 
+    // Returns None for tiles off the board
     pub fn get(&self, row: usize, col: usize) -> Option<Tile> {
-        let index = self.coord_to_index(row, col);
-        Some(self.board[index])
+        if row < self.size && col < self.size {
+            Some(self.board[self.coord_to_index(row, col)])
+        } else {
+            None
+        }
     }
 
-    // Sets a tile to the given color
+    // Sets a tile to the given color. Placing on an empty tile updates the groups incrementally,
+    // while removing or recoloring a stone has to rebuild them
     pub fn set(&mut self, row: usize, col: usize, color: Tile) {
         let index = self.coord_to_index(row, col);
-        self.board[index] = color
+        let previous = self.board[index];
+        self.board[index] = color;
+        self.hash ^= zobrist_key(index, previous) ^ zobrist_key(index, color);
+
+        if previous == Tile::Empty {
+            self.connect(index);
+            self.update_winner();
+        } else if previous != color {
+            self.rebuild_groups();
+        }
     }
 
-    pub fn set_move(&mut self, mv: &str, color: Tile) {
-        if mv.eq("swap") {
-            return;
+    // Plays a move for the given color. Nothing is changed if the move isn't valid. A swap
+    // follows the stone-swap convention: the first stone moves to its mirror image across the
+    // long diagonal and becomes the swapper's
+    pub fn set_move(&mut self, mv: &str, color: Tile) -> Result<Move, MoveError> {
+        let parsed = self.check_move(mv)?;
+
+        match parsed {
+            Move::Place(coord) => self.set(coord.row, coord.col, color),
+            Move::Swap => {
+                let first = self.first_stone().unwrap();
+                self.set(first.row, first.col, Tile::Empty);
+                self.set(first.col, first.row, color);
+            }
         }
-        let index = self.move_to_index(mv);
-        self.board[index] = color
+        Ok(parsed)
+    }
+
+    // Parses a move and makes sure it can be played on this board. Swapping needs exactly one
+    // stone on the board, but only the game knows whether it's also the second move
+    pub fn check_move(&self, mv: &str) -> Result<Move, MoveError> {
+        let parsed = Move::parse(mv, self.size)?;
+
+        match parsed {
+            Move::Place(coord) if self.get(coord.row, coord.col) != Some(Tile::Empty) => {
+                Err(MoveError::Occupied(coord))
+            }
+            Move::Swap if self.board.iter().filter(|&&tile| tile != Tile::Empty).count() != 1 => {
+                Err(MoveError::MisplacedSwap)
+            }
+            _ => Ok(parsed),
+        }
+    }
+
+    // How many tiles are still free
+    pub fn empty_count(&self) -> usize {
+        self.board.iter().filter(|&&tile| tile == Tile::Empty).count()
     }
 
-    // Returns true when the specified tile is empty
+    // The stone closest to a1, reading left to right then top to bottom. The only one when
+    // swapping
+    pub fn first_stone(&self) -> Option<Coord> {
+        self.board.iter()
+            .position(|&tile| tile != Tile::Empty)
+            .map(|i| Coord::new(i / self.size, i % self.size))
+    }
+
+    // Returns true when the move is well formed and the specified tile is empty
     pub fn is_valid_move(&self, mv: &str) -> bool {
-        mv.eq("swap") || self.board[self.move_to_index(mv)] == Tile::Empty
+        self.check_move(mv).is_ok()
     }
 
     // Returns the color of the player who won, empty otherwise
     pub fn has_win(&self) -> Tile {
-        if self.is_black_win() {
-            Tile::Black
-        } else if self.is_white_win() {
-            Tile::White
-        } else {
-            Tile::Empty
+        self.winner
+    }
+
+    // The 2 to 6 tiles next to a tile
+    pub fn neighbours(&self, coord: Coord) -> Vec<Coord> {
+        self.get_adj(coord.row, coord.col)
+            .into_iter()
+            .map(|i| Coord::new(i / self.size, i % self.size))
+            .collect()
+    }
+
+    // An equivalent position. Moves on the new board are remapped with the same symmetry
+    pub fn transformed(&self, symmetry: Symmetry) -> Self {
+        let mut board = Self::new(self.size as u8);
+
+        for (i, &tile) in self.board.iter().enumerate() {
+            let (r, c) = self.index_to_coord(i).unwrap();
+            let to = symmetry.coord(Coord::new(r, c), self.size);
+            board.set(to.row, to.col, symmetry.tile(tile));
         }
+        board
+    }
+
+    // 64 bit Zobrist hash of the stones on the board. Equal boards always have equal hashes, no
+    // matter the order the stones were placed in. The side to move isn't included, as it follows
+    // from the number of stones
+    pub fn zobrist(&self) -> u64 {
+        self.hash
     }
 
+    // The smallest hash of all the boards equivalent under a symmetry. A position and its
+    // transposed, color swapped copy share a canonical hash, even though a different player is to
+    // move in each, as they're the same position from the point of view of the side to move
+    pub fn canonical_zobrist(&self) -> u64 {
+        Symmetry::ALL.iter()
+            .map(|&symmetry| {
+                self.board.iter().enumerate().fold(0, |hash, (i, &tile)| {
+                    let (r, c) = self.index_to_coord(i).unwrap();
+                    let to = symmetry.coord(Coord::new(r, c), self.size);
+                    hash ^ zobrist_key(self.coord_to_index(to.row, to.col), symmetry.tile(tile))
+                })
+            })
+            .min()
+            .unwrap()
+    }
+
+    // The board turned 180 degrees
+    pub fn rotated(&self) -> Self {
+        self.transformed(Symmetry::Rotation)
+    }
+
+    // The board transposed, with black and white swapped
+    pub fn color_swapped(&self) -> Self {
+        self.transformed(Symmetry::ColorSwap)
+    }
+
+    // Returns the shortest chain of tiles joining the color's two edges, if there is one. Black's
+    // path runs from the top row down, white's from the left column across
+    pub fn winning_path(&self, color: Tile) -> Option<Vec<Coord>> {
+        let is_start: fn(usize, usize, usize) -> bool = match color {
+            Tile::Black => |r, _, _| r == 0,
+            Tile::White => |_, c, _| c == 0,
+            Tile::Empty => return None,
+        };
+        let is_end: fn(usize, usize, usize) -> bool = match color {
+            Tile::Black => |r, _, s| r == s - 1,
+            _ => |_, c, s| c == s - 1,
+        };
+
+        // Breadth first search from every stone on the starting edge at once
+        let mut parents = vec![None; self.board.len()];
+        let mut seen = vec![false; self.board.len()];
+        let mut queue = std::collections::VecDeque::new();
+
+        for (i, &tile) in self.board.iter().enumerate() {
+            let (r, c) = self.index_to_coord(i).unwrap();
+
+            if tile == color && is_start(r, c, self.size) {
+                seen[i] = true;
+                queue.push_back(i);
+            }
+        }
+
+        while let Some(i) = queue.pop_front() {
+            let (r, c) = self.index_to_coord(i).unwrap();
+
+            if is_end(r, c, self.size) {
+                let mut path = vec![Coord::new(r, c)];
+                let mut current = i;
+
+                while let Some(parent) = parents[current] {
+                    let (r, c) = self.index_to_coord(parent).unwrap();
+                    path.push(Coord::new(r, c));
+                    current = parent;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for adj in self.get_adj(r, c) {
+                if !seen[adj] && self.board[adj] == color {
+                    seen[adj] = true;
+                    parents[adj] = Some(i);
+                    queue.push_back(adj);
+                }
+            }
+        }
+        None
+    }
+
+    // Queenbee's two-distance between the color's edges: a tile's distance from an edge is one
+    // more than the second closest of its neighbours, since the opponent can always block the
+    // closest one. Returns the smallest sum of both distances over the empty tiles, so lower is
+    // better. 0 once the color has won, None once the opponent has cut it off
+    pub fn two_distance(&self, color: Tile) -> Option<usize> {
+        if color == Tile::Empty {
+            return None;
+        }
+        if self.has_win() == color {
+            return Some(0);
+        }
+
+        let neighbours: Vec<_> = (0..self.board.len())
+            .map(|i| (self.board[i] == Tile::Empty).then(|| self.empty_neighbours(i, color)))
+            .collect();
+        let from_start = Self::two_distances(&neighbours, 0);
+        let from_end = Self::two_distances(&neighbours, 1);
+
+        (0..self.board.len())
+            .filter_map(|i| Some(from_start[i]? + from_end[i]?))
+            .min()
+    }
+
+    // Anshelevich's circuit resistance between the color's edges. Every empty tile is a 1 ohm
+    // resistor, the color's stones are wires and the opponent's stones are cut out. 0 once the
+    // color has won, infinite once the opponent has cut it off
+    pub fn resistance(&self, color: Tile) -> f64 {
+        if color == Tile::Empty {
+            return f64::INFINITY;
+        }
+
+        // Groups of stones, along with the edges they touch, are a single node of the circuit
+        let len = self.board.len();
+        let (source, sink) = (len, len + 1);
+        let mut nodes = DisjointSet::new(len + 2);
+
+        for i in 0..len {
+            if self.board[i] != color {
+                continue;
+            }
+            let (r, c) = self.index_to_coord(i).unwrap();
+            let (start, end) = self.touches_edges(r, c, color);
+
+            if start {
+                nodes.union(i, source);
+            }
+            if end {
+                nodes.union(i, sink);
+            }
+            for adj in self.get_adj(r, c) {
+                if self.board[adj] == color {
+                    nodes.union(i, adj);
+                }
+            }
+        }
+        if nodes.is_connected(source, sink) {
+            return 0.0;
+        }
+
+        // Wires between neighbouring nodes, with the conductance of the tiles they go through
+        let mut wires = Vec::new();
+        for i in (0..len).filter(|&i| self.board[i] == Tile::Empty) {
+            let (r, c) = self.index_to_coord(i).unwrap();
+            let (start, end) = self.touches_edges(r, c, color);
+
+            if start {
+                wires.push((i, nodes.find(source), 1.0));
+            }
+            if end {
+                wires.push((i, nodes.find(sink), 1.0));
+            }
+            for adj in self.get_adj(r, c) {
+                match self.board[adj] {
+                    Tile::Empty if adj > i => wires.push((i, adj, 0.5)),
+                    tile if tile == color => wires.push((i, nodes.find(adj), 1.0)),
+                    _ => {}
+                }
+            }
+        }
+
+        let current = circuit_current(len + 2, &wires, nodes.find(source), nodes.find(sink));
+        1.0 / current
+    }
+
+    // How much better the color stands than its opponent, as the log of the ratio of their
+    // resistances. Positive when the color is ahead, infinite once the game is decided
+    pub fn evaluate(&self, color: Tile) -> f64 {
+        let own = self.resistance(color);
+        let theirs = self.resistance(color.opponent());
+
+        if own == 0.0 || theirs == f64::INFINITY {
+            f64::INFINITY
+        } else if theirs == 0.0 || own == f64::INFINITY {
+            f64::NEG_INFINITY
+        } else {
+            (theirs / own).ln()
+        }
+    }
 
     // Returns an array of all indicies adjacent to a given hex. That's 2-5 indicies
     fn get_adj(&self, row: usize, column: usize) -> Vec<usize> {
@@ -120,73 +649,196 @@ impl Board {
         }
     }
 
-    // Converts a move (ex: "a1") to the board's index
-    fn move_to_index(&self, mv: &str) -> usize {
-        let r = mv.chars().nth(0).unwrap() as usize - 97;  // First letter
-        let c = mv[1..].parse::<usize>().unwrap() - 1;    // All following digits
+    fn edge(&self, edge: usize) -> usize {
+        self.board.len() + edge
+    }
 
-        self.coord_to_index(r, c)
+    // Joins a newly placed stone with its neighbours of the same color and any edges it touches
+    fn connect(&mut self, index: usize) {
+        let color = self.board[index];
+        let (r, c) = self.index_to_coord(index).unwrap();
+
+        let edges = match color {
+            Tile::Black => [(r == 0, TOP), (r == self.size - 1, BOTTOM)],
+            Tile::White => [(c == 0, LEFT), (c == self.size - 1, RIGHT)],
+            Tile::Empty => return,
+        };
+
+        for (touches, edge) in edges {
+            if touches {
+                let edge = self.edge(edge);
+                self.groups.union(index, edge);
+            }
+        }
+
+        for adj in self.get_adj(r, c) {
+            if self.board[adj] == color {
+                self.groups.union(index, adj);
+            }
+        }
     }
 
-    fn is_black_win(&self) -> bool {
-        let mut dfs_tree = vec![DFS::Unvisited; self.size.pow(2)];
+    fn rebuild_groups(&mut self) {
+        self.groups = DisjointSet::new(self.board.len() + EDGE_COUNT);
 
-        for start_col in 0..self.size {
-            let start = self.coord_to_index(0, start_col);
+        for index in 0..self.board.len() {
+            self.connect(index);
+        }
+        self.update_winner();
+    }
+
+    fn update_winner(&mut self) {
+        let (top, bottom) = (self.edge(TOP), self.edge(BOTTOM));
+        let (left, right) = (self.edge(LEFT), self.edge(RIGHT));
+
+        self.winner = if self.groups.is_connected(top, bottom) {
+            Tile::Black
+        } else if self.groups.is_connected(left, right) {
+            Tile::White
+        } else {
+            Tile::Empty
+        };
+    }
 
-            if self.board[start] == Tile::Black && dfs_tree[start] == DFS::Unvisited {
-                for adj in self.get_adj(0, start_col).into_iter() {
-                    if self.board[adj] == Tile::Black {
-                        dfs_tree[start] = DFS::Visited;
+    // Whether the tile is on the color's starting and ending edges
+    fn touches_edges(&self, r: usize, c: usize, color: Tile) -> (bool, bool) {
+        match color {
+            Tile::Black => (r == 0, r == self.size - 1),
+            Tile::White => (c == 0, c == self.size - 1),
+            Tile::Empty => (false, false),
+        }
+    }
 
-                        if self.has_path(adj, Tile::Black, &mut dfs_tree) {
-                            return true;
-                        }
-                    }
+    // The empty tiles an empty tile can reach directly or through a group of the color's stones,
+    // and which of the color's edges it reaches the same way
+    fn empty_neighbours(&self, index: usize, color: Tile) -> (Vec<usize>, [bool; 2]) {
+        let mut neighbours = Vec::new();
+        let mut edges = [false; 2];
+        let mut seen = vec![false; self.board.len()];
+        let mut stack = vec![index];
+        seen[index] = true;
+
+        while let Some(i) = stack.pop() {
+            let (r, c) = self.index_to_coord(i).unwrap();
+            let (start, end) = self.touches_edges(r, c, color);
+            edges[0] |= start;
+            edges[1] |= end;
+
+            for adj in self.get_adj(r, c) {
+                if seen[adj] {
+                    continue;
+                }
+                seen[adj] = true;
+
+                match self.board[adj] {
+                    Tile::Empty => neighbours.push(adj),
+                    tile if tile == color => stack.push(adj),
+                    _ => {}
                 }
             }
         }
-        false
+        (neighbours, edges)
     }
 
-    fn is_white_win(&self) -> bool {
-        let mut dfs_tree = vec![DFS::Unvisited; self.size.pow(2)];
-
-        for start_row in 0..self.size {
-            let start = self.coord_to_index(start_row, 0);
+    // Two-distance of every empty tile from one of the color's edges, given each empty tile's
+    // neighbours. Tiles next to the edge are 1 away, the rest are filled in one distance at a time
+    fn two_distances(neighbours: &[Option<(Vec<usize>, [bool; 2])>], edge: usize) -> Vec<Option<usize>> {
+        let mut distances: Vec<_> = neighbours.iter()
+            .map(|n| n.as_ref().and_then(|(_, edges)| edges[edge].then_some(1)))
+            .collect();
+
+        for distance in 1.. {
+            let next: Vec<_> = (0..neighbours.len())
+                .filter(|&i| distances[i].is_none())
+                .filter(|&i| match &neighbours[i] {
+                    Some((adj, _)) => adj.iter().filter(|&&a| distances[a].is_some_and(|d| d <= distance)).count() >= 2,
+                    None => false,
+                })
+                .collect();
+
+            if next.is_empty() {
+                break;
+            }
+            for i in next {
+                distances[i] = Some(distance + 1);
+            }
+        }
+        distances
+    }
+}
 
-            if self.board[start] == Tile::White && dfs_tree[start] == DFS::Unvisited {
-                for adj in self.get_adj(start_row, 0).into_iter() {
-                    if self.board[adj] == Tile::White {
-                        dfs_tree[start] = DFS::Visited;
+// Current flowing from `source`, held at 1 volt, to `sink`, held at 0, through wires given as the
+// nodes they join and their conductance. The voltages of the other nodes come from solving
+// Kirchhoff's equations with conjugate gradients, which converges quickly as they're sparse
+fn circuit_current(nodes: usize, wires: &[(usize, usize, f64)], source: usize, sink: usize) -> f64 {
+    let mut adjacent = vec![Vec::new(); nodes];
+    for &(a, b, conductance) in wires {
+        adjacent[a].push((b, conductance));
+        adjacent[b].push((a, conductance));
+    }
 
-                        if self.has_path(adj, Tile::White, &mut dfs_tree) {
-                            return true;
-                        }
-                    }
-                }
+    // Only nodes connected to the source carry any current
+    let mut reachable = vec![false; nodes];
+    let mut stack = vec![source];
+    reachable[source] = true;
+    while let Some(node) = stack.pop() {
+        for &(adj, _) in &adjacent[node] {
+            if !reachable[adj] && adj != sink {
+                reachable[adj] = true;
+                stack.push(adj);
             }
         }
-        false
+    }
+    if !adjacent[sink].iter().any(|&(adj, _)| reachable[adj]) {
+        return 0.0;
     }
 
-    fn has_path(&self, from: usize, color: Tile, dfs_tree: &mut Vec<DFS>) -> bool {
-        let (r, c) = self.index_to_coord(from).unwrap();
+    let unknown = |node: usize| reachable[node] && node != source;
+    let laplacian = |x: &[f64]| -> Vec<f64> {
+        (0..nodes)
+            .map(|i| if unknown(i) {
+                adjacent[i].iter().map(|&(j, g)| g * (x[i] - if unknown(j) { x[j] } else { 0.0 })).sum()
+            } else {
+                0.0
+            })
+            .collect()
+    };
+    let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f64>();
 
-        if color == Tile::White && c == self.size - 1 || color == Tile::Black && r == self.size - 1 {
-            return true;
+    // The source's voltage moves to the right hand side
+    let b: Vec<f64> = (0..nodes)
+        .map(|i| if unknown(i) {
+            adjacent[i].iter().filter(|&&(j, _)| j == source).map(|&(_, g)| g).sum()
+        } else {
+            0.0
+        })
+        .collect();
+
+    let mut voltages = vec![0.0; nodes];
+    let mut residual = b.clone();
+    let mut direction = residual.clone();
+    let mut error = dot(&residual, &residual);
+    let tolerance = dot(&b, &b) * 1e-24;
+
+    for _ in 0..nodes {
+        if error <= tolerance {
+            break;
         }
+        let product = laplacian(&direction);
+        let step = error / dot(&direction, &product);
 
-        dfs_tree[from] = DFS::Visiting;
-        for adj in self.get_adj(r, c).into_iter() {
-            if dfs_tree[adj] == DFS::Unvisited && self.board[adj] == color {
-                if self.has_path(adj, color, dfs_tree) {
-                    return true;
-                }
-            }
+        for i in 0..nodes {
+            voltages[i] += step * direction[i];
+            residual[i] -= step * product[i];
         }
-        false
+        let next_error = dot(&residual, &residual);
+        for i in 0..nodes {
+            direction[i] = residual[i] + next_error / error * direction[i];
+        }
+        error = next_error;
     }
+
+    adjacent[source].iter().map(|&(adj, g)| g * (1.0 - if unknown(adj) { voltages[adj] } else { 0.0 })).sum()
 }
 
 impl std::fmt::Display for Board {
@@ -196,18 +848,30 @@ impl std::fmt::Display for Board {
     //   . . B .
     //    W . W B
     // ------------------
+    //
+    // The alternate form, `{:#}`, writes the winner's path in lowercase. Ex: "b . . ."
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = if f.alternate() {
+            self.winning_path(self.has_win()).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
         for r in 0..self.size {
             write!(f, "{}", " ".repeat(r))?;
 
             for c in 0..self.size {
+                let on_path = path.contains(&Coord::new(r, c));
+
                 match self.board[self.coord_to_index(r, c)] {
+                    Tile::Black if on_path => write!(f, "b ")?,
+                    Tile::White if on_path => write!(f, "w ")?,
                     Tile::Black => write!(f, "B ")?,
                     Tile::White => write!(f, "W ")?,
                     Tile::Empty => write!(f, ". ")?,
                 }
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
 
         write!(f, "{}", "-".repeat(18))
@@ -236,35 +900,41 @@ mod board_testing {
 
         board.set(3,2, Tile::Empty);
         assert_eq!(board.get(3,2), Some(Tile::Empty));
+
+        assert_eq!(board.get(4,0), None);
+        assert_eq!(board.get(0,4), None);
     }
 
     #[test]
     fn adjacent_tiles() {
-        let mut board = Board::new(5);
+        let board = Board::new(5);
         {
             let r = 2; let c = 2;
 
             let adjs = board.get_adj(r, c);
-            let expected = vec![7, 8, 11, 13, 16, 17];
+            let expected = [7, 8, 11, 13, 16, 17];
 
             assert_eq!(adjs.iter().sum::<usize>(), expected.iter().sum::<usize>());
         }
         {
             let r = 1; let c = 4;
 
-            let mut adjs = board.get_adj(r, c);
-            let mut expected = vec![4, 8, 13, 14];
+            let adjs = board.get_adj(r, c);
+            let expected = [4, 8, 13, 14];
 
             assert_eq!(adjs.iter().sum::<usize>(), expected.iter().sum::<usize>());
         }
         {
             let r = 4; let c = 4;
 
-            let mut adjs = board.get_adj(r, c);
-            let mut expected = vec![19, 23];
+            let adjs = board.get_adj(r, c);
+            let expected = [19, 23];
 
             assert_eq!(adjs.iter().sum::<usize>(), expected.iter().sum::<usize>());
         }
+
+        assert_eq!(board.neighbours(Coord::new(0, 0)), vec![Coord::new(1, 0), Coord::new(0, 1)]);
+        assert_eq!(board.neighbours(Coord::new(2, 2)).len(), 6);
     }
 
     #[test]
@@ -291,6 +961,74 @@ mod board_testing {
         let expected2 ="B B B B \n . B W . \n  . . B . \n   W . W B \n------------------";
 
         assert_eq!(format!("{}", board), expected2);
+        assert_eq!(board.to_compressed(), "BBBB|.BW.|..B.|W.WB|");
+        assert_eq!(Board::from(&board.to_compressed()).to_string(), expected2);
+
+        assert_eq!(Board::parse(" BBBB|.BW.|..B.|W.WB|\n").unwrap().to_compressed(), board.to_compressed());
+        assert!(Board::parse("BBBB|.BW.|..B.|").is_err());
+        assert!(Board::parse("B.|.X|").is_err());
+        assert!(Board::parse("").is_err());
+
+        let other = Board::from("BBBB|.BW.|W.B.|W..B|");
+        assert_eq!(board.diff(&other), vec![Coord::new(2, 0), Coord::new(3, 2)]);
+        assert!(board.diff(&board).is_empty());
+    }
+
+    #[test]
+    fn parse_moves() {
+        assert_eq!(Move::parse("a1", 4), Ok(Move::Place(Coord::new(0, 0))));
+        assert_eq!(Move::parse("c4", 4), Ok(Move::Place(Coord::new(2, 3))));
+        assert_eq!(Move::parse("z26", 26), Ok(Move::Place(Coord::new(25, 25))));
+        assert_eq!(Move::parse("swap", 4), Ok(Move::Swap));
+
+        assert_eq!(Move::parse("", 4), Err(MoveError::Empty));
+        assert_eq!(Move::parse("A1", 4), Err(MoveError::BadLetter('A')));
+        assert_eq!(Move::parse("1a", 4), Err(MoveError::BadLetter('1')));
+        assert_eq!(Move::parse("a", 4), Err(MoveError::MissingNumber));
+        assert_eq!(Move::parse("a1x", 4), Err(MoveError::TrailingGarbage("x".to_string())));
+        assert_eq!(Move::parse("swap\r", 4), Err(MoveError::MissingNumber));
+        assert_eq!(Move::parse("a1\r", 4), Err(MoveError::TrailingGarbage("\r".to_string())));
+        assert_eq!(Move::parse("e1", 4), Err(MoveError::RowOutOfRange('e')));
+        assert_eq!(Move::parse("z99", 10), Err(MoveError::RowOutOfRange('z')));
+        assert_eq!(Move::parse("a0", 4), Err(MoveError::ColumnOutOfRange("0".to_string())));
+        assert_eq!(Move::parse("a5", 4), Err(MoveError::ColumnOutOfRange("5".to_string())));
+        assert_eq!(Move::parse("a99999999999999999999999", 4),
+            Err(MoveError::ColumnOutOfRange("99999999999999999999999".to_string())));
+    }
+
+    #[test]
+    fn coord_round_trip() {
+        for r in 0..26 {
+            for c in 0..26 {
+                let coord = Coord::new(r, c);
+                assert_eq!(Coord::parse(&coord.to_string(), 26), Ok(coord));
+            }
+        }
+    }
+
+    #[test]
+    fn setting_moves() {
+        let mut board = Board::new(4);
+
+        assert_eq!(board.set_move("b3", Tile::Black), Ok(Move::Place(Coord::new(1, 2))));
+        assert_eq!(board.get(1, 2), Some(Tile::Black));
+
+        assert_eq!(board.set_move("b3", Tile::White), Err(MoveError::Occupied(Coord::new(1, 2))));
+        assert_eq!(board.get(1, 2), Some(Tile::Black));
+
+        assert!(board.set_move("q1", Tile::White).is_err());
+        assert!(!board.is_valid_move("b3"));
+        assert!(!board.is_valid_move("A1"));
+        assert!(board.is_valid_move("a1"));
+
+        assert_eq!(board.set_move("swap", Tile::White), Ok(Move::Swap));
+        assert_eq!(board.get(1, 2), Some(Tile::Empty));
+        assert_eq!(board.get(2, 1), Some(Tile::White));
+        assert_eq!(board.first_stone(), Some(Coord::new(2, 1)));
+
+        board.set(0, 0, Tile::Black);
+        assert_eq!(board.set_move("swap", Tile::White), Err(MoveError::MisplacedSwap));
+        assert_eq!(Board::new(4).check_move("swap"), Err(MoveError::MisplacedSwap));
     }
 
     #[test]
@@ -368,4 +1106,220 @@ mod board_testing {
 
         }
     }
+
+    #[test]
+    fn check_win_long_snake() {
+        let size = 26;
+        let mut board = Board::new(size as u8);
+
+        // Full rows joined by a single stone alternating between the right and left ends
+        for r in 0..size {
+            if r % 2 == 0 {
+                for c in 0..size {
+                    board.set(r, c, Tile::Black);
+                }
+            } else if r % 4 == 1 {
+                board.set(r, size - 1, Tile::Black);
+            } else {
+                board.set(r, 0, Tile::Black);
+            }
+        }
+        assert_eq!(board.has_win(), Tile::Black);
+
+        board.set(size / 2 + 1, 0, Tile::White);
+        assert_eq!(board.has_win(), Tile::Black);
+
+        board.set(size / 2 - 1, size - 1, Tile::Empty);
+        assert_eq!(board.has_win(), Tile::Empty);
+
+        board.set(size / 2 - 1, size - 1, Tile::Black);
+        assert_eq!(board.has_win(), Tile::Black);
+    }
+
+    #[test]
+    fn check_win_from_bot_output() {
+        assert_eq!(Board::from("B..|B..|B..|").has_win(), Tile::Black);
+        assert_eq!(Board::from("...|WWW|...|").has_win(), Tile::White);
+        assert_eq!(Board::from("B.W|BW.|.B.|").has_win(), Tile::Empty);
+    }
+
+    #[test]
+    fn winning_paths() {
+        let mut board = Board::new(4);
+        assert_eq!(board.winning_path(Tile::Black), None);
+        assert_eq!(board.winning_path(Tile::Empty), None);
+
+        // A dead end branch at c1 and a longer detour through b2 shouldn't end up in the path
+        for (r, c) in [(0, 1), (1, 0), (1, 1), (2, 0), (2, 1), (3, 0)] {
+            board.set(r, c, Tile::Black);
+        }
+        board.set(0, 3, Tile::White);
+
+        assert_eq!(board.has_win(), Tile::Black);
+        assert_eq!(board.winning_path(Tile::White), None);
+        assert_eq!(board.winning_path(Tile::Black),
+            Some(vec![Coord::new(0, 1), Coord::new(1, 0), Coord::new(2, 0), Coord::new(3, 0)]));
+
+        let expected = ". b . W \n b B . . \n  b B . . \n   b . . . \n------------------";
+        assert_eq!(format!("{:#}", board), expected);
+        assert_eq!(format!("{}", board), expected.to_uppercase());
+    }
+
+    #[test]
+    fn winning_path_is_shortest() {
+        let mut board = Board::new(5);
+
+        // A snake through rows a and c, then a shortcut straight down the first column
+        for c in 0..5 {
+            board.set(0, c, Tile::Black);
+            board.set(2, c, Tile::Black);
+        }
+        board.set(1, 4, Tile::Black);
+        board.set(3, 0, Tile::Black);
+        board.set(4, 0, Tile::Black);
+        assert_eq!(board.winning_path(Tile::Black).map(|p| p.len()), Some(7));
+
+        board.set(1, 0, Tile::Black);
+        assert_eq!(board.winning_path(Tile::Black),
+            Some((0..5).map(|r| Coord::new(r, 0)).collect()));
+    }
+
+    #[test]
+    fn symmetries() {
+        let size = 4;
+        assert_eq!(Symmetry::Rotation.coord(Coord::new(0, 1), size), Coord::new(3, 2));
+        assert_eq!(Symmetry::ColorSwap.coord(Coord::new(0, 1), size), Coord::new(1, 0));
+        assert_eq!(Symmetry::RotatedColorSwap.coord(Coord::new(0, 1), size), Coord::new(2, 3));
+        assert_eq!(Symmetry::ColorSwap.apply(Move::Swap, size), Move::Swap);
+
+        for symmetry in Symmetry::ALL {
+            for r in 0..size {
+                for c in 0..size {
+                    // Every symmetry undoes itself
+                    let coord = Coord::new(r, c);
+                    assert_eq!(symmetry.coord(symmetry.coord(coord, size), size), coord);
+                }
+            }
+        }
+
+        // Black's winning column becomes a white winning row
+        let board = Board::from("B...|BW..|B.W.|B...|");
+        let rotated = board.rotated();
+        let swapped = board.color_swapped();
+
+        assert_eq!(rotated.get(3, 3), Some(Tile::Black));
+        assert_eq!(rotated.get(2, 2), Some(Tile::White));
+        assert_eq!(rotated.has_win(), Tile::Black);
+        assert_eq!(swapped.get(0, 3), Some(Tile::White));
+        assert_eq!(swapped.get(1, 1), Some(Tile::Black));
+        assert_eq!(swapped.has_win(), Tile::White);
+        assert_eq!(board.transformed(Symmetry::RotatedColorSwap).has_win(), Tile::White);
+
+        let board = Board::from("B.W.|BW..|..W.|B...|");
+        for symmetry in Symmetry::ALL {
+            assert_eq!(board.transformed(symmetry).transformed(symmetry).to_string(), board.to_string());
+        }
+    }
+
+    #[test]
+    fn zobrist_hashes() {
+        let mut board = Board::new(5);
+        assert_eq!(board.zobrist(), 0);
+
+        board.set_move("a1", Tile::Black).unwrap();
+        board.set_move("c3", Tile::White).unwrap();
+        board.set_move("e2", Tile::Black).unwrap();
+        let hash = board.zobrist();
+        assert_ne!(hash, 0);
+
+        // Move order doesn't matter, and the hash matches one computed from scratch
+        let mut other = Board::new(5);
+        other.set_move("e2", Tile::Black).unwrap();
+        other.set_move("c3", Tile::White).unwrap();
+        other.set_move("a1", Tile::Black).unwrap();
+        assert_eq!(other.zobrist(), hash);
+        assert_eq!(Board::from(&board.to_compressed()).zobrist(), hash);
+
+        // Recoloring and removing stones are undone exactly
+        other.set(2, 2, Tile::Black);
+        assert_ne!(other.zobrist(), hash);
+        other.set(2, 2, Tile::White);
+        assert_eq!(other.zobrist(), hash);
+        other.set(0, 0, Tile::Empty);
+        other.set(2, 2, Tile::Empty);
+        other.set(4, 1, Tile::Empty);
+        assert_eq!(other.zobrist(), 0);
+
+        // Same color, different place
+        let mut moved = Board::new(5);
+        moved.set_move("a2", Tile::Black).unwrap();
+        let mut original = Board::new(5);
+        original.set_move("a1", Tile::Black).unwrap();
+        assert_ne!(moved.zobrist(), original.zobrist());
+    }
+
+    #[test]
+    fn canonical_zobrist_hashes() {
+        let board = Board::from("B.W..|.B...|..W..|.....|W...B|");
+
+        for symmetry in Symmetry::ALL {
+            let transformed = board.transformed(symmetry);
+            assert_eq!(transformed.canonical_zobrist(), board.canonical_zobrist());
+        }
+        assert!(Symmetry::ALL.iter().any(|&s| board.transformed(s).zobrist() == board.canonical_zobrist()));
+
+        let different = Board::from("B.W..|.B...|..W..|.....|W..B.|");
+        assert_ne!(different.canonical_zobrist(), board.canonical_zobrist());
+    }
+
+    #[test]
+    fn evaluations() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+        // One empty tile between both edges, then a small network solved by hand
+        assert!(close(Board::new(1).resistance(Tile::Black), 2.0));
+        assert_eq!(Board::new(1).two_distance(Tile::Black), Some(2));
+        assert!(close(Board::from("B.|..|").resistance(Tile::Black), 7.0 / 6.0));
+
+        // Black's column only needs (2, 0), white's stones are still two tiles from the left
+        let board = Board::from("B..|B.W|..W|");
+        assert_eq!(board.two_distance(Tile::Black), Some(2));
+        assert!(board.two_distance(Tile::White) > Some(2));
+        assert!(board.resistance(Tile::Black) < board.resistance(Tile::White));
+        assert!(board.evaluate(Tile::Black) > 0.0);
+        assert!(close(board.evaluate(Tile::Black), -board.evaluate(Tile::White)));
+
+        // Decided games
+        let board = Board::from("B..|BWW|B..|");
+        assert_eq!(board.two_distance(Tile::Black), Some(0));
+        assert_eq!(board.two_distance(Tile::White), None);
+        assert_eq!(board.resistance(Tile::Black), 0.0);
+        assert_eq!(board.resistance(Tile::White), f64::INFINITY);
+        assert_eq!(board.evaluate(Tile::Black), f64::INFINITY);
+        assert_eq!(board.evaluate(Tile::White), f64::NEG_INFINITY);
+        assert_eq!(Board::from("...|WWW|...|").two_distance(Tile::Black), None);
+
+        // Both colors stand the same on an empty board, and a stone only helps its own color
+        for size in [2, 5, 9] {
+            let mut board = Board::new(size);
+            assert_eq!(board.two_distance(Tile::Black), board.two_distance(Tile::White));
+            assert!(close(board.resistance(Tile::Black), board.resistance(Tile::White)));
+
+            let (black, white) = (board.resistance(Tile::Black), board.resistance(Tile::White));
+            let center = size as usize / 2;
+            board.set(center, center, Tile::Black);
+            assert!(board.resistance(Tile::Black) < black);
+            assert!(board.resistance(Tile::White) > white);
+            assert!(board.two_distance(Tile::Black) < board.two_distance(Tile::White));
+        }
+
+        // Swapping colors and transposing swaps the evaluations
+        let board = Board::from("..W..|.B...|..BW.|W....|...B.|");
+        for symmetry in Symmetry::ALL {
+            let transformed = board.transformed(symmetry);
+            let color = symmetry.tile(Tile::Black);
+            assert_eq!(transformed.two_distance(color), board.two_distance(Tile::Black));
+            assert!(close(transformed.resistance(color), board.resistance(Tile::Black)));
+        }
+    }
 }
@@ -0,0 +1,298 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::mem;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::units::{parse_duration, parse_size};
+use crate::protocol::Message;
+
+// How long a bot gets to exit by itself after `quit` before it's killed
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(1);
+
+// Lines of stderr kept for reporting a crash
+const STDERR_TAIL: usize = 10;
+
+// Why a bot didn't reply to a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoReply {
+    Timeout,
+    // The bot closed its stdout, which almost always means it exited
+    Closed,
+}
+
+// A file to write a bot's stderr to, instead of passing it through to ours. Anything past the
+// first `cap` bytes is left out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StderrLog {
+    pub path: PathBuf,
+    pub cap: u64,
+}
+
+// Resource limits for bot processes, the same for every bot so that each one gets a fair share of
+// the machine
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct Limits {
+    /// Most memory each bot may map, as RLIMIT_AS. Allocations past it fail. Ex: 2G
+    #[clap(long, value_parser = parse_size)]
+    pub memory_limit: Option<u64>,
+    /// Most CPU time each bot may use in a game, as RLIMIT_CPU, rounded up to whole seconds. Bots
+    /// that go over are killed and forfeit with a crash. Ex: 10m
+    #[clap(long, value_parser = parse_duration)]
+    pub cpu_limit: Option<Duration>,
+    /// Most processes and threads, as RLIMIT_NPROC. Linux counts every process of the user
+    /// running the controller against it, not just the bot's, so leave room for the rest
+    #[clap(long)]
+    pub process_limit: Option<u64>,
+    /// Niceness of every bot, from -20 to 19. Only root can go below 0
+    #[clap(long, allow_hyphen_values = true)]
+    pub nice: Option<i32>,
+    /// CPUs the bot that starts as black is pinned to. Ex: 0,1
+    #[clap(long, value_delimiter = ',')]
+    pub black_cpus: Vec<usize>,
+    /// CPUs the bot that starts as white is pinned to. Ex: 2,3
+    #[clap(long, value_delimiter = ',')]
+    pub white_cpus: Vec<usize>,
+}
+
+// What a bot used over a whole game, measured once it exits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    // User and system time together
+    pub cpu_time: Duration,
+    // Largest resident set size, in kilobytes
+    pub peak_rss: u64,
+}
+
+// A running bot process. Its stdout is read line by line on a separate thread, so that waiting
+// for a reply can time out instead of blocking forever. Its stderr goes to its log file or ours,
+// with the last few lines kept in case it crashes
+#[derive(Debug)]
+pub struct Bot {
+    process: Child,
+    lines: Receiver<String>,
+    stderr: Arc<Mutex<VecDeque<String>>>,
+    // Set once the process has exited and been waited for
+    exit: Option<(ExitStatus, Usage)>,
+    // Time left on this bot's game clock, if the game has one
+    pub clock: Option<Duration>,
+}
+
+impl Bot {
+    pub fn spawn(bot_path: &Path, color: &str, log: Option<&StderrLog>, limits: &Limits) -> io::Result<Self> {
+        let mut file = match log {
+            Some(log) => Some((File::create(&log.path)?, log.cap)),
+            None => None,
+        };
+        let mut written = 0;
+        let mut cut_off = false;
+
+        let mut command = Command::new(bot_path);
+        command.arg(color)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        limit(&mut command, limits, if color == "black" { &limits.black_cpus } else { &limits.white_cpus })?;
+        let mut process = command.spawn()?;
+
+        let (sender, lines) = mpsc::channel();
+        let mut reader = BufReader::new(process.stdout.take().unwrap());
+
+        // Replies that aren't UTF-8 are read anyway, to be rejected as malformed moves
+        thread::spawn(move || loop {
+            let mut line = Vec::new();
+
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => if sender.send(String::from_utf8_lossy(&line).into_owned()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let stderr = Arc::new(Mutex::new(VecDeque::new()));
+        let tail = Arc::clone(&stderr);
+        let mut reader = BufReader::new(process.stderr.take().unwrap());
+
+        thread::spawn(move || loop {
+            let mut line = Vec::new();
+
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    // The log stops at the cap, with a note that the rest was cut off the first time
+                    // anything is left out
+                    match &mut file {
+                        Some((file, cap)) if !cut_off => {
+                            let kept = line.len().min((*cap - written) as usize);
+                            let _ = file.write_all(&line[..kept]);
+                            written += kept as u64;
+
+                            if kept < line.len() {
+                                let _ = writeln!(file, "\n[stderr cut off after {} bytes]", cap);
+                                cut_off = true;
+                            }
+                        }
+                        Some(_) => (),
+                        None => eprint!("{}", String::from_utf8_lossy(&line)),
+                    }
+
+                    let line = String::from_utf8_lossy(&line);
+                    let mut tail = tail.lock().unwrap();
+                    if tail.len() == STDERR_TAIL {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line.trim_end().to_string());
+                }
+            }
+        });
+
+        Ok(Self { process, lines, stderr, exit: None, clock: None })
+    }
+
+    // Writing to a bot that has exited fails quietly. The next `request` finds out it's gone
+    pub fn send(&mut self, message: Message) {
+        if let Some(bot_in) = self.process.stdin.as_mut() {
+            let _ = writeln!(bot_in, "{}", message);
+        }
+    }
+
+    // Sends a message and waits for a single line in reply, for as long as the timeout if there
+    // is one
+    pub fn request(&mut self, message: Message, timeout: Option<Duration>) -> Result<String, NoReply> {
+        self.send(message);
+
+        match timeout {
+            Some(timeout) => self.lines.recv_timeout(timeout).map_err(|e| match e {
+                RecvTimeoutError::Timeout => NoReply::Timeout,
+                RecvTimeoutError::Disconnected => NoReply::Closed,
+            }),
+            None => self.lines.recv().map_err(|_| NoReply::Closed),
+        }
+    }
+
+    // How the bot ended up, along with the end of its stderr. Waits a little for a bot that
+    // closed its stdout to exit. Ex: "exit status: 101, stderr: thread 'main' panicked..."
+    pub fn crash_report(&mut self) -> String {
+        let start = Instant::now();
+        let status = loop {
+            match self.reap(false) {
+                Some(status) => break status.to_string(),
+                None if start.elapsed() < SHUTDOWN_GRACE => thread::sleep(Duration::from_millis(10)),
+                None => break "still running with its stdout closed".to_string(),
+            }
+        };
+
+        // Give the stderr thread a moment to catch up with a process that just exited
+        thread::sleep(Duration::from_millis(50));
+        let tail = self.stderr.lock().unwrap();
+
+        if tail.is_empty() {
+            status
+        } else {
+            format!("{}, stderr: {}", status, tail.iter().cloned().collect::<Vec<_>>().join(" / "))
+        }
+    }
+
+    // Tells the bot the game is over. Bots that don't exit shortly after are killed
+    pub fn shutdown(&mut self) {
+        if let Some(bot_in) = self.process.stdin.as_mut() {
+            let _ = writeln!(bot_in, "{}", Message::Quit);
+        }
+        drop(self.process.stdin.take());
+
+        let start = Instant::now();
+        while start.elapsed() < SHUTDOWN_GRACE {
+            match self.reap(false) {
+                Some(_) => return,
+                None => thread::sleep(Duration::from_millis(10)),
+            }
+        }
+
+        let _ = self.process.kill();
+        self.reap(true);
+    }
+
+    // What the bot used, once it has exited
+    pub fn usage(&self) -> Option<Usage> {
+        self.exit.map(|(_, usage)| usage)
+    }
+
+    // Waits for the process to exit, or only checks whether it has. The process is waited for
+    // with wait4 instead of through `Child`, to get its resource usage along with its status
+    fn reap(&mut self, block: bool) -> Option<ExitStatus> {
+        if self.exit.is_none() {
+            let mut status = 0;
+            let mut usage: libc::rusage = unsafe { mem::zeroed() };
+            let flags = if block { 0 } else { libc::WNOHANG };
+
+            let pid = loop {
+                let pid = unsafe { libc::wait4(self.process.id() as libc::pid_t, &mut status, flags, &mut usage) };
+                if pid >= 0 || io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+                    break pid;
+                }
+            };
+
+            if pid > 0 {
+                let time = |t: libc::timeval| Duration::new(t.tv_sec as u64, t.tv_usec as u32 * 1000);
+                let usage = Usage {
+                    cpu_time: time(usage.ru_utime) + time(usage.ru_stime),
+                    peak_rss: usage.ru_maxrss as u64,
+                };
+                self.exit = Some((ExitStatus::from_raw(status), usage));
+            }
+        }
+        self.exit.map(|(status, _)| status)
+    }
+}
+
+// Sets up the limits to apply to the bot between forking and starting it. Anything that can't be
+// applied keeps the bot from starting
+fn limit(command: &mut Command, limits: &Limits, cpus: &[usize]) -> io::Result<()> {
+    let memory = limits.memory_limit;
+    let cpu_seconds = limits.cpu_limit.map(|limit| limit.as_secs() + (limit.subsec_nanos() > 0) as u64);
+    let processes = limits.process_limit;
+    let nice = limits.nice;
+
+    let mut cpu_set: Option<libc::cpu_set_t> = None;
+    for &cpu in cpus {
+        if cpu >= libc::CPU_SETSIZE as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("there's no CPU {}", cpu)));
+        }
+        let set = cpu_set.get_or_insert(unsafe { mem::zeroed() });
+        unsafe { libc::CPU_SET(cpu, set) };
+    }
+
+    let rlimit = |soft: u64, hard: u64| libc::rlimit { rlim_cur: soft as libc::rlim_t, rlim_max: hard as libc::rlim_t };
+    let check = |result: libc::c_int| if result == 0 { Ok(()) } else { Err(io::Error::last_os_error()) };
+
+    // Only async-signal-safe system calls, as this runs in the forked child
+    unsafe {
+        command.pre_exec(move || {
+            if let Some(bytes) = memory {
+                check(libc::setrlimit(libc::RLIMIT_AS, &rlimit(bytes, bytes)))?;
+            }
+            // The soft limit sends SIGXCPU, and the hard one a second later SIGKILL
+            if let Some(seconds) = cpu_seconds {
+                check(libc::setrlimit(libc::RLIMIT_CPU, &rlimit(seconds, seconds + 1)))?;
+            }
+            if let Some(processes) = processes {
+                check(libc::setrlimit(libc::RLIMIT_NPROC, &rlimit(processes, processes)))?;
+            }
+            if let Some(nice) = nice {
+                check(libc::setpriority(libc::PRIO_PROCESS, 0, nice))?;
+            }
+            if let Some(set) = &cpu_set {
+                check(libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), set))?;
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
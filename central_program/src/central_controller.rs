@@ -1,12 +1,16 @@
 // This program is written with unix in mind. No clue what'll happen on windows...
-mod board;
 mod testing;
 
 use testing::BotTest;
+use sentience_validator::tournament::{self, Tournament};
+use sentience_validator::{dataset, game_data, ratings};
+use sentience_validator::solver::{self, Solver};
 
-use std::process::{self, Command, Stdio, Child};
-use std::io::{self, BufRead, BufReader, Write};
-use board::{Board, Tile};
+use std::process;
+use std::io::{self, BufRead};
+use sentience_validator::{Board, Bot, Message, Tile};
+use sentience_validator::board::MAX_SIZE;
+use sentience_validator::game::{EndReason, Game, GameResult, Referee, TimeControl};
 
 use std::path::PathBuf;
 use clap::{Parser, Subcommand};
@@ -35,7 +39,24 @@ enum Commands {
     /// Let two bots face off in hex
     Matchup {
         /// The size of the board squared. Ex: 11
+        #[clap(value_parser = clap::value_parser!(u8).range(1..=MAX_SIZE as i64))]
+        size: u8,
+        /// Path to the main executable for the black bot (top->bottom)
+        #[clap(action)]
+        black_bot: PathBuf,
+        /// Path to the main executable for the white bot (left->right)
         #[clap(action)]
+        white_bot: PathBuf,
+        #[clap(flatten)]
+        time: TimeControl,
+        #[clap(flatten)]
+        referee: Referee,
+    },
+    /// Play a whole game without any input, then print a `result` line. Exits with 0 when
+    /// black wins, 1 when white wins and 2 when the game ended in a forfeit or error
+    Play {
+        /// The size of the board squared. Ex: 11
+        #[clap(value_parser = clap::value_parser!(u8).range(1..=MAX_SIZE as i64))]
         size: u8,
         /// Path to the main executable for the black bot (top->bottom)
         #[clap(action)]
@@ -43,6 +64,120 @@ enum Commands {
         /// Path to the main executable for the white bot (left->right)
         #[clap(action)]
         white_bot: PathBuf,
+        #[clap(flatten)]
+        time: TimeControl,
+        #[clap(flatten)]
+        referee: Referee,
+    },
+    /// Run a tournament between several bots, then print a crosstable and ratings
+    Tournament {
+        /// The size of the board squared. Ex: 11
+        #[clap(value_parser = clap::value_parser!(u8).range(1..=MAX_SIZE as i64))]
+        size: u8,
+        /// Paths to the main executable of every bot in the tournament
+        #[clap(action, required = true, min_values = 2)]
+        bots: Vec<PathBuf>,
+        /// How the bots are paired up
+        #[clap(long, value_enum, default_value = "round-robin")]
+        format: tournament::Format,
+        /// Games played by each pairing, alternating colors. Double round robins play this many
+        /// games with each color
+        #[clap(long, default_value = "1")]
+        games: usize,
+        /// Rounds in the tournament. Round robins play every pairing again each round, and default
+        /// to 1. Swiss tournaments default to log2 of the number of bots, rounded up
+        #[clap(long)]
+        rounds: Option<usize>,
+        /// File to store the result of each game in. Running the same command again with this
+        /// file resumes an interrupted tournament
+        #[clap(long)]
+        results: Option<PathBuf>,
+        #[clap(flatten)]
+        time: TimeControl,
+        #[clap(flatten)]
+        referee: Referee,
+    },
+    /// Print the crosstable and ratings of a tournament's results file
+    Ratings {
+        /// File written by `tournament --results`
+        #[clap(action)]
+        results: PathBuf,
+    },
+    /// Check a file of recorded games for lines that don't parse and games that don't add up.
+    /// Exits with 0 if every line is a valid game and 1 otherwise
+    Validate {
+        /// File in the format of training_data/game_data.txt
+        #[clap(action)]
+        games: PathBuf,
+        /// The size of the board squared
+        #[clap(long, default_value = "10", value_parser = clap::value_parser!(u8).range(1..=MAX_SIZE as i64))]
+        size: u8,
+    },
+    /// Turn a file of recorded games into NumPy training tensors: planes.npy, moves.npy and
+    /// outcomes.npy. Games that don't pass `validate` are left out, and listed with the reason
+    Dataset {
+        /// File in the format of training_data/game_data.txt
+        #[clap(action)]
+        games: PathBuf,
+        /// Directory to write the .npy files to
+        #[clap(action)]
+        out_dir: PathBuf,
+        /// The size of the board squared
+        #[clap(long, default_value = "10", value_parser = clap::value_parser!(u8).range(1..=MAX_SIZE as i64))]
+        size: u8,
+        /// Moves at the start of every game to leave out. The first black and white moves of
+        /// training_data/game_data.txt were picked at random
+        #[clap(long, default_value = "2")]
+        skip: usize,
+        /// Also write symmetric copies of every position: turned 180 degrees, or all four
+        /// including the transposed board with black and white swapped
+        #[clap(long, value_enum, default_value = "none")]
+        augment: dataset::Augment,
+    },
+    /// Play a new version of a bot against an old one until a sequential probability ratio test
+    /// decides. Exits with 0 if the new bot is stronger, 1 if it isn't and 2 if undecided
+    Sprt {
+        /// The size of the board squared. Ex: 11
+        #[clap(value_parser = clap::value_parser!(u8).range(1..=MAX_SIZE as i64))]
+        size: u8,
+        /// Path to the main executable of the new version
+        #[clap(action)]
+        new_bot: PathBuf,
+        /// Path to the main executable of the old version
+        #[clap(action)]
+        old_bot: PathBuf,
+        /// Elo difference of the null hypothesis, that the new bot isn't an improvement
+        #[clap(long, default_value = "0", allow_hyphen_values = true)]
+        elo0: f64,
+        /// Elo difference of the alternative hypothesis, that the new bot is stronger
+        #[clap(long, default_value = "30", allow_hyphen_values = true)]
+        elo1: f64,
+        /// Chance of wrongly deciding the new bot is stronger
+        #[clap(long, default_value = "0.05")]
+        alpha: f64,
+        /// Chance of wrongly deciding the new bot isn't stronger
+        #[clap(long, default_value = "0.05")]
+        beta: f64,
+        /// Give up without a decision after this many games
+        #[clap(long, default_value = "1000")]
+        max_games: usize,
+        #[clap(flatten)]
+        time: TimeControl,
+        #[clap(flatten)]
+        referee: Referee,
+    },
+    /// Prove who wins a position with perfect play, and every winning move. Meant for boards up
+    /// to about 6x6. Exits with 0 when black wins, 1 when white wins and 2 if it gave up
+    Solve {
+        /// The position, one row at a time as bots print it for show_board. Ex: "...|.B.|..W|"
+        #[clap(action)]
+        position: String,
+        /// Side to move. Defaults to black, unless black has more stones on the board
+        #[clap(long, value_enum)]
+        to_move: Option<Color>,
+        /// Give up after searching this many positions
+        #[clap(long)]
+        max_nodes: Option<u64>,
     },
 }
 
@@ -69,11 +204,161 @@ fn main() {
             let mut bot_test = BotTest::new(color, bot_path);
             bot_test.test();
         }
-        Commands::Matchup { size, black_bot, white_bot } => {
-            let black = spawn_bot(black_bot, "black");
-            let white = spawn_bot(white_bot, "white");
-            print_repl_help();
-            run_match(size, black, white);
+        Commands::Matchup { size, black_bot, white_bot, time, referee } => {
+            match Game::start(size, &black_bot, &white_bot, time, referee) {
+                Ok(game) => {
+                    print_repl_help();
+                    run_match(game);
+                }
+                Err(result) => {
+                    println!("result {}", result);
+                    process::exit(result.exit_code());
+                }
+            }
+        }
+        Commands::Play { size, black_bot, white_bot, time, referee } => {
+            let result = match Game::start(size, &black_bot, &white_bot, time, referee) {
+                Ok(mut game) => {
+                    let mut result = game.play_out();
+                    result.usage = game.shutdown();
+                    announce_result(&game.board, &result);
+                    result
+                }
+                Err(result) => *result,
+            };
+
+            println!("result {}", result);
+            process::exit(result.exit_code());
+        }
+        Commands::Tournament { size, bots, format, games, rounds, results, time, referee } => {
+            let mut tournament = Tournament::new(bots, size, format, rounds, games, time);
+            tournament.referee = referee;
+
+            if let Some(results) = results {
+                if let Err(e) = tournament.resume_from(results) {
+                    eprintln!("Failed to read tournament results: {}", e);
+                    process::exit(2);
+                }
+            }
+            if let Err(e) = tournament.run() {
+                eprintln!("Failed to save tournament results: {}", e);
+                process::exit(2);
+            }
+            tournament.print_report();
+        }
+        Commands::Ratings { results } => {
+            let (bots, records) = match tournament::read_results(&results) {
+                Ok(results) => results,
+                Err(e) => {
+                    eprintln!("Failed to read tournament results: {}", e);
+                    process::exit(2);
+                }
+            };
+            let names: Vec<String> = (0..bots.len()).map(|bot| tournament::bot_name(&bots, bot)).collect();
+            let games: Vec<(usize, usize)> = records.iter().filter_map(|r| r.outcome()).collect();
+
+            ratings::print_report(&names, &games);
+        }
+        Commands::Validate { games, size } => {
+            let lines = match game_data::read_games(&games) {
+                Ok(lines) => lines,
+                Err(e) => {
+                    eprintln!("Failed to read games: {}", e);
+                    process::exit(2);
+                }
+            };
+
+            let (mut valid, mut unreadable, mut invalid) = (0, 0, 0);
+            for (number, line) in lines.iter().enumerate() {
+                match line {
+                    Err(e) => {
+                        println!("line {}: {}", number + 1, e);
+                        unreadable += 1;
+                    }
+                    Ok(record) => {
+                        let problems = record.validate(size);
+                        for problem in &problems {
+                            println!("line {}: {}", number + 1, problem);
+                        }
+                        if problems.is_empty() { valid += 1 } else { invalid += 1 }
+                    }
+                }
+            }
+
+            println!("validate lines={} valid={} invalid={} unreadable={}", lines.len(), valid,
+                invalid, unreadable);
+            process::exit(if valid == lines.len() { 0 } else { 1 });
+        }
+        Commands::Dataset { games, out_dir, size, skip, augment } => {
+            match dataset::export(&games, &out_dir, size, skip, augment) {
+                Ok(summary) => {
+                    for (number, reason) in &summary.skipped {
+                        println!("line {}: skipped, {}", number, reason);
+                    }
+                    println!("dataset games={} skipped={} positions={}", summary.games,
+                        summary.skipped.len(), summary.positions);
+                }
+                Err(e) => {
+                    eprintln!("Failed to write dataset: {}", e);
+                    process::exit(2);
+                }
+            }
+        }
+        Commands::Sprt { size, new_bot, old_bot, elo0, elo1, alpha, beta, max_games, time, referee } => {
+            let sprt = match ratings::Sprt::new(elo0, elo1, alpha, beta) {
+                Ok(sprt) => sprt,
+                Err(e) => {
+                    eprintln!("Bad SPRT parameters: {}", e);
+                    process::exit(2);
+                }
+            };
+            let (decision, wins, losses) = tournament::run_sprt(size, &new_bot, &old_bot, sprt,
+                max_games, &time, &referee);
+
+            let (name, code) = match decision {
+                ratings::SprtDecision::AcceptH1 => ("h1", 0),
+                ratings::SprtDecision::AcceptH0 => ("h0", 1),
+                ratings::SprtDecision::Continue => ("none", 2),
+            };
+            println!("sprt decision={} wins={} losses={} llr={:.3}", name, wins, losses,
+                sprt.llr(wins, losses));
+            process::exit(code);
+        }
+        Commands::Solve { position, to_move, max_nodes } => {
+            let board = match Board::parse(&position) {
+                Ok(board) if board.size() > solver::MAX_SIZE => {
+                    eprintln!("Bad position: boards over {0}x{0} are too big to solve", solver::MAX_SIZE);
+                    process::exit(2);
+                }
+                Ok(board) => board,
+                Err(e) => {
+                    eprintln!("Bad position: {}", e);
+                    process::exit(2);
+                }
+            };
+            let to_move = match to_move {
+                Some(Color::Black) => Tile::Black,
+                Some(Color::White) => Tile::White,
+                None => solver::side_to_move(&board),
+            };
+
+            let mut solver = Solver::new();
+            solver.max_nodes = max_nodes;
+            let solution = solver.solve(&board, to_move);
+            let name = |color| if color == Tile::Black { "black" } else { "white" };
+
+            match solution {
+                Some(solution) => {
+                    let moves: Vec<String> = solution.winning_moves.iter().map(|m| m.to_string()).collect();
+                    println!("solve winner={} to_move={} winning_moves={} nodes={}", name(solution.winner),
+                        name(to_move), moves.join(","), solver.nodes);
+                    process::exit(if solution.winner == Tile::Black { 0 } else { 1 });
+                }
+                None => {
+                    println!("solve winner=unknown to_move={} nodes={}", name(to_move), solver.nodes);
+                    process::exit(2);
+                }
+            }
         }
     }
 }
@@ -93,134 +378,71 @@ fn print_repl_help() {
     ].join("\n"));
 }
 
-fn run_match(size: u8, mut black: Child, mut white: Child) {
-    let mut board = Board::new(size);
-    let mut is_black_turn = true;
-
-    init_board(size, &mut black, &mut white);
-
+fn run_match(mut game: Game) {
     let stdin = io::stdin();
 
-    for line in stdin.lock().lines().map(|l| l.unwrap()) {
+    'repl: for line in stdin.lock().lines().map(|l| l.unwrap()) {
         if "help" == line || "h" == line {
             print_repl_help();
         } else if "check" == line || "c" == line {
-            match board.has_win() {
+            match game.board.has_win() {
                 Tile::Empty => println!("Nobody's won... yet"),
                 color => println!("{} has won!", color),
             }
         } else if "show" == line || "s" == line {
-            println!("{}", board);
+            println!("{:#}", game.board);
         } else if "showall" == line || "S" == line {
-            println!("{}\nCentral board ----------------\n{}", "=".repeat(20), board);
-            print_bot_board(&mut black, Tile::Black);
-            print_bot_board(&mut white, Tile::White);
+            println!("{}\nCentral board ----------------\n{:#}", "=".repeat(20), game.board);
+            print_bot_board(&mut game.black, Tile::Black);
+            print_bot_board(&mut game.white, Tile::White);
             println!("{}", "=".repeat(20));
         } else if "exit" == line || "quit" == line {
             println!("Shutting down");
+            game.shutdown();
             process::exit(0);
         } else if "next" == line || "n" == line {
-            let mv = play_turn(is_black_turn, &mut board, &mut black, &mut white);
-
-            if board.has_win() == Tile::Empty {
-                if mv != String::from("swap") {
-                    is_black_turn = !is_black_turn;
-                } else {
-                    let tmp = black;
-                    black = white;
-                    white = tmp;
-                }
-            } else if board.has_win() == Tile::Black {
-                println!("Black has won");
-                break;
-            } else {
-                println!("White has won");
+            if let Some(result) = game.step() {
+                announce_result(&game.board, &result);
                 break;
             }
         } else if line.len() >= 5 && "run " == &line[..4] && line[4..].parse::<usize>().is_ok() {
             for _ in 0..line[4..].parse::<usize>().unwrap() {
-                let mv = play_turn(is_black_turn, &mut board, &mut black, &mut white);
-
-                if board.has_win() == Tile::Empty {
-                    if mv != String::from("swap") {
-                        is_black_turn = !is_black_turn;
-                    } else {
-                        let tmp = black;
-                        black = white;
-                        white = tmp;
-                    }
-                } else if board.has_win() == Tile::Black {
-                    println!("Black has won");
-                    break;
-                } else {
-                    println!("White has won");
-                    break;
+                if let Some(result) = game.step() {
+                    announce_result(&game.board, &result);
+                    break 'repl;
                 }
             }
         } else {
             println!("Command `{}` not found. See \"help\" for a list of commands", line);
         }
     }
+    game.shutdown();
 }
 
+// Prints the winner along with the chain of tiles that won them the game
+fn announce_result(board: &Board, result: &GameResult) {
+    // The winner is named by the color they started with, which a color swap changes
+    match board.has_win() {
+        color if color != Tile::Empty && color != result.winner => println!(
+            "{} has won, playing {} after swapping colors", result.winner, color.to_string().to_lowercase()),
+        _ if result.winner == Tile::Empty => println!("Nobody wins: both bots were out of sync"),
+        _ => println!("{} has won", result.winner),
+    }
 
+    if result.reason == EndReason::Connection {
+        let path = result.path.iter().map(|coord| coord.to_string()).collect::<Vec<_>>();
 
-fn spawn_bot(bot_path: PathBuf, color: &str) -> Child {
-        Command::new(bot_path)
-        .arg(color)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .expect("Failed to startup bot")
-}
-
-fn send_message(bot: &mut Child, message: &str) {
-    let bot_in = bot.stdin.as_mut().unwrap();
-    write!(bot_in, "{}\n", message).unwrap();
-}
-
-fn get_response(bot: &mut Child, message: &str) -> String {
-    send_message(bot, message);
-
-    let bot_out = bot.stdout.as_mut().unwrap();
-    let mut reader = BufReader::new(bot_out);
-    let mut response = String::new();
-
-    reader.read_line(&mut response).unwrap();
-    response
-}
-
-fn init_board(size: u8, black: &mut Child, white: &mut Child) {
-    send_message(black, &format!("init_board {}\n", size));
-    send_message(white, &format!("init_board {}\n", size));
-}
-
-fn print_bot_board(bot: &mut Child, color: Tile) {
-    let response = get_response(bot, "show_board");
-    println!("{} board ------------------\n{}", color, Board::from(&response));
+        println!("Winning path: {}", path.join(" "));
+        println!("{:#}", board);
+    }
 }
 
-fn play_turn(is_black_turn: bool, board: &mut Board, black: &mut Child, white: &mut Child) -> String {
-    let (this_turn_bot, next_turn_bot, this_turn_color) = if is_black_turn {
-        (black, white, Tile::Black)
-    } else {
-        (white, black, Tile::White)
+fn print_bot_board(bot: &mut Bot, color: Tile) {
+    let board = match bot.request(Message::ShowBoard, None) {
+        Ok(response) => Board::parse(&response).map(|board| board.to_string()).unwrap_or_else(|e| {
+            format!("`{}` isn't a board: {}", response.trim().escape_debug(), e)
+        }),
+        Err(_) => format!("No board, the bot crashed ({})", bot.crash_report()),
     };
-
-    let response = get_response(this_turn_bot, "make_move");
-    let mv = response.trim();
-
-    if board.is_valid_move(&mv) {
-        println!("{}'s move: {}", this_turn_color, mv);
-        board.set_move(&mv, this_turn_color);
-        if mv.eq("swap") {
-            send_message(next_turn_bot, "swap");
-        } else {
-            send_message(next_turn_bot, &format!("seto {}\n", mv));
-        }
-    } else {
-        panic!("Black bot returned invalid move `{}`!", mv);
-    }
-
-    String::from(mv)
+    println!("{} board ------------------\n{}", color, board);
 }
@@ -0,0 +1,441 @@
+// Virtual connections: two groups of stones, or a group and one of its edges, that stay connected
+// however the opponent plays, as long as every move into the connection's carrier is answered
+// inside it. Search bots prune with them, and they show why a position is won before the stones
+// actually touch.
+//
+// Only single connections are found: two-bridges between groups, two free tiles between a group
+// and its edge, and the ziggurat edge template. Stones on the fourth row or further out aren't
+// connected to their edge, even where a larger template such as IV-1a would hold, so a win that
+// relies on one is missed. `winning_chain` strings the connections together from one edge to the
+// other
+use std::collections::HashSet;
+
+use crate::board::{Board, Coord, Symmetry, Tile};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Edge {
+    // The color's two edges. Black connects top to bottom, white left to right. Empty tiles don't
+    // connect anything
+    pub fn of(color: Tile) -> Option<[Self; 2]> {
+        match color {
+            Tile::Black => Some([Self::Top, Self::Bottom]),
+            Tile::White => Some([Self::Left, Self::Right]),
+            Tile::Empty => None,
+        }
+    }
+
+    // Takes the bottom edge to this one, and this one back to the bottom
+    fn symmetry(self) -> Symmetry {
+        match self {
+            Self::Bottom => Symmetry::Identity,
+            Self::Top => Symmetry::Rotation,
+            Self::Right => Symmetry::ColorSwap,
+            Self::Left => Symmetry::RotatedColorSwap,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum End {
+    // Index into `Connections::groups`
+    Group(usize),
+    Edge(Edge),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    // The group is already on the edge
+    Adjacent,
+    // Two empty tiles that each join both ends, so taking one leaves the other
+    Bridge,
+    // Third row edge template IIIa, also called 4-3-2 after the width of its rows
+    Ziggurat,
+}
+
+// Edge templates from a single stone and the empty tiles they need, written for the bottom edge.
+// Tiles are (rows from the edge, columns along it) relative to the stone, which is `rows` rows
+// from the edge. The tiles one row closer to the edge than (d, x) are (d - 1, x - 1) and (d - 1, x).
+// Mirror images are found as well
+struct Template {
+    kind: Kind,
+    rows: i32,
+    carrier: &'static [(i32, i32)],
+}
+
+const TEMPLATES: [Template; 1] = [
+    Template {
+        kind: Kind::Ziggurat,
+        rows: 2,
+        carrier: &[(2, 1), (1, -1), (1, 0), (1, 1), (0, -2), (0, -1), (0, 0), (0, 1)],
+    },
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connection {
+    pub group: usize,
+    pub to: End,
+    pub kind: Kind,
+    // Empty tiles the connection needs
+    pub carrier: Vec<Coord>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Connections {
+    pub color: Tile,
+    // The color's groups of touching stones
+    pub groups: Vec<Vec<Coord>>,
+    // The connection with the smallest carrier between each pair of groups that has one, and
+    // between each group and each of its edges
+    pub connections: Vec<Connection>,
+}
+
+impl Connections {
+    // None for `Tile::Empty`, which has no stones to connect
+    pub fn find(board: &Board, color: Tile) -> Option<Self> {
+        let edges = Edge::of(color)?;
+        let groups = find_groups(board, color);
+        let mut connections = Vec::new();
+
+        for (i, group) in groups.iter().enumerate() {
+            for edge in edges {
+                if let Some((kind, carrier)) = edge_connection(board, color, group, edge) {
+                    connections.push(Connection { group: i, to: End::Edge(edge), kind, carrier });
+                }
+            }
+
+            let free = liberties(board, group);
+            for (j, other) in groups.iter().enumerate().skip(i + 1) {
+                let shared: Vec<Coord> = liberties(board, other).into_iter().filter(|c| free.contains(c)).collect();
+
+                if shared.len() >= 2 {
+                    let carrier = shared[..2].to_vec();
+                    connections.push(Connection { group: i, to: End::Group(j), kind: Kind::Bridge, carrier });
+                }
+            }
+        }
+
+        Some(Self { color, groups, connections })
+    }
+
+    pub fn group_of(&self, coord: Coord) -> Option<usize> {
+        self.groups.iter().position(|group| group.contains(&coord))
+    }
+
+    pub fn to_edge(&self, group: usize, edge: Edge) -> Option<&Connection> {
+        self.connections.iter().find(|c| c.group == group && c.to == End::Edge(edge))
+    }
+
+    pub fn between(&self, a: usize, b: usize) -> Option<&Connection> {
+        self.connections.iter().find(|c| {
+            (c.group == a && c.to == End::Group(b)) || (c.group == b && c.to == End::Group(a))
+        })
+    }
+
+    // Connections joining the color's edges through its groups, with no tile in two carriers.
+    // Every move the opponent makes in one carrier can then be answered in the same one, so the
+    // color has won. A chain can be missed when groups have several ways to connect, but one
+    // that's found always holds
+    pub fn winning_chain(&self) -> Option<Vec<&Connection>> {
+        let [start, end] = Edge::of(self.color)?;
+        let mut visited = vec![false; self.groups.len()];
+        let mut chain = Vec::new();
+        let mut used = HashSet::new();
+
+        for first in self.connections.iter().filter(|c| c.to == End::Edge(start)) {
+            if self.extend(first.group, first, end, &mut visited, &mut chain, &mut used) {
+                return Some(chain);
+            }
+        }
+        None
+    }
+
+    // The tiles the opponent has to play in to stand a chance, once the color has virtually won:
+    // every tile in the carriers of the winning chain. None if no winning chain was found
+    pub fn must_play(&self) -> Option<Vec<Coord>> {
+        let mut tiles: Vec<Coord> = self.winning_chain()?.iter().flat_map(|c| c.carrier.clone()).collect();
+        tiles.sort_by_key(|c| (c.row, c.col));
+        Some(tiles)
+    }
+
+    // Depth first search for the rest of a chain, once `group` is reached through `via`. Groups
+    // stay visited after backing out of them, which keeps the search linear
+    fn extend<'a>(&'a self, group: usize, via: &'a Connection, end: Edge, visited: &mut [bool],
+        chain: &mut Vec<&'a Connection>, used: &mut HashSet<Coord>) -> bool
+    {
+        if visited[group] || via.carrier.iter().any(|c| used.contains(c)) {
+            return false;
+        }
+        visited[group] = true;
+        used.extend(via.carrier.iter().copied());
+        chain.push(via);
+
+        if let Some(last) = self.to_edge(group, end) {
+            if last.carrier.iter().all(|c| !used.contains(c)) {
+                chain.push(last);
+                return true;
+            }
+        }
+        for connection in &self.connections {
+            let next = match connection.to {
+                End::Group(j) if connection.group == group => j,
+                End::Group(j) if j == group => connection.group,
+                _ => continue,
+            };
+            if self.extend(next, connection, end, visited, chain, used) {
+                return true;
+            }
+        }
+
+        chain.pop();
+        for c in &via.carrier {
+            used.remove(c);
+        }
+        false
+    }
+}
+
+fn find_groups(board: &Board, color: Tile) -> Vec<Vec<Coord>> {
+    let size = board.size();
+    let mut seen = vec![false; size * size];
+    let mut groups = Vec::new();
+
+    for i in 0..size * size {
+        if seen[i] || board.get(i / size, i % size) != Some(color) {
+            continue;
+        }
+
+        let mut group = Vec::new();
+        let mut stack = vec![Coord::new(i / size, i % size)];
+        seen[i] = true;
+
+        while let Some(coord) = stack.pop() {
+            group.push(coord);
+
+            for adj in board.neighbours(coord) {
+                let index = adj.row * size + adj.col;
+                if !seen[index] && board.get(adj.row, adj.col) == Some(color) {
+                    seen[index] = true;
+                    stack.push(adj);
+                }
+            }
+        }
+        group.sort_by_key(|c| (c.row, c.col));
+        groups.push(group);
+    }
+    groups
+}
+
+// Empty tiles next to the group
+fn liberties(board: &Board, group: &[Coord]) -> Vec<Coord> {
+    let mut liberties: Vec<Coord> = group.iter()
+        .flat_map(|&coord| board.neighbours(coord))
+        .filter(|adj| board.get(adj.row, adj.col) == Some(Tile::Empty))
+        .collect();
+
+    liberties.sort_by_key(|c| (c.row, c.col));
+    liberties.dedup();
+    liberties
+}
+
+// The best way the group reaches the edge, along with the empty tiles it needs
+fn edge_connection(board: &Board, color: Tile, group: &[Coord], edge: Edge) -> Option<(Kind, Vec<Coord>)> {
+    let size = board.size();
+    let symmetry = edge.symmetry();
+    let on_edge = |coord: Coord| symmetry.coord(coord, size).row == size - 1;
+
+    if group.iter().any(|&coord| on_edge(coord)) {
+        return Some((Kind::Adjacent, Vec::new()));
+    }
+
+    let free: Vec<Coord> = liberties(board, group).into_iter().filter(|&c| on_edge(c)).collect();
+    if free.len() >= 2 {
+        return Some((Kind::Bridge, free[..2].to_vec()));
+    }
+
+    let mut best: Option<(Kind, Vec<Coord>)> = None;
+    for &stone in group {
+        // Where the stone is when the edge is turned to the bottom
+        let bottom = symmetry.coord(stone, size);
+        let rows = (size - 1 - bottom.row) as i32;
+
+        for template in TEMPLATES.iter().filter(|t| t.rows == rows) {
+            for mirrored in [false, true] {
+                let carrier: Option<Vec<Coord>> = template.carrier.iter()
+                    .map(|&(d, x)| {
+                        let x = if mirrored { d - x - rows } else { x };
+                        let col = bottom.col as i32 + x;
+                        (0..size as i32).contains(&col)
+                            .then(|| symmetry.coord(Coord::new(size - 1 - d as usize, col as usize), size))
+                    })
+                    .collect();
+
+                let holds = carrier.as_ref().is_some_and(|carrier| {
+                    carrier.iter().all(|c| matches!(board.get(c.row, c.col), Some(t) if t == color || t == Tile::Empty))
+                });
+                if !holds {
+                    continue;
+                }
+
+                let carrier: Vec<Coord> = carrier.unwrap().into_iter()
+                    .filter(|c| board.get(c.row, c.col) == Some(Tile::Empty))
+                    .collect();
+                if best.as_ref().is_none_or(|(_, b)| carrier.len() < b.len()) {
+                    best = Some((template.kind, carrier));
+                }
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod connections_testing {
+    use super::*;
+    use std::collections::HashMap;
+
+    // Whether black connects `stone` to the bottom edge with white moving first and only the
+    // carrier left to play in, by trying every move
+    fn template_holds(board: &Board, stone: Coord, carrier: &[Coord]) -> bool {
+        fn connected(board: &Board, stone: Coord) -> bool {
+            let size = board.size();
+            let mut seen = vec![stone];
+            let mut stack = vec![stone];
+
+            while let Some(coord) = stack.pop() {
+                if coord.row == size - 1 {
+                    return true;
+                }
+                for adj in board.neighbours(coord) {
+                    if board.get(adj.row, adj.col) == Some(Tile::Black) && !seen.contains(&adj) {
+                        seen.push(adj);
+                        stack.push(adj);
+                    }
+                }
+            }
+            false
+        }
+
+        fn search(board: &mut Board, stone: Coord, carrier: &[Coord], white: bool, memo: &mut HashMap<u64, bool>) -> bool {
+            if connected(board, stone) {
+                return true;
+            }
+            if let Some(&holds) = memo.get(&board.zobrist()) {
+                return holds;
+            }
+
+            let empty: Vec<Coord> = carrier.iter().copied().filter(|c| board.get(c.row, c.col) == Some(Tile::Empty)).collect();
+            if empty.is_empty() {
+                return false;
+            }
+
+            let (color, wins) = if white { (Tile::White, false) } else { (Tile::Black, true) };
+            let mut result = !wins;
+            for tile in empty {
+                board.set(tile.row, tile.col, color);
+                let holds = search(board, stone, carrier, !white, memo);
+                board.set(tile.row, tile.col, Tile::Empty);
+
+                if holds == wins {
+                    result = wins;
+                    break;
+                }
+            }
+            memo.insert(board.zobrist(), result);
+            result
+        }
+
+        let mut board = board.clone();
+        search(&mut board, stone, carrier, true, &mut HashMap::new())
+    }
+
+    #[test]
+    fn templates_hold() {
+        for template in &TEMPLATES {
+            for mirrored in [false, true] {
+                // Plenty of room around the template on the bottom edge, with the rest of the
+                // board taken by white
+                let size = 9;
+                let stone = Coord::new(size - 1 - template.rows as usize, 4);
+                let carrier: Vec<Coord> = template.carrier.iter()
+                    .map(|&(d, x)| {
+                        let x = if mirrored { d - x - template.rows } else { x };
+                        Coord::new(size - 1 - d as usize, (4 + x) as usize)
+                    })
+                    .collect();
+
+                let mut board = Board::new(size as u8);
+                for r in 0..size {
+                    for c in 0..size {
+                        board.set(r, c, Tile::White);
+                    }
+                }
+                board.set(stone.row, stone.col, Tile::Black);
+                for &tile in &carrier {
+                    board.set(tile.row, tile.col, Tile::Empty);
+                }
+                assert!(template_holds(&board, stone, &carrier), "{:?}", template.kind);
+
+                // Every tile of the carrier is needed
+                for i in 0..carrier.len() {
+                    let mut smaller = carrier.clone();
+                    let removed = smaller.remove(i);
+                    board.set(removed.row, removed.col, Tile::White);
+                    assert!(!template_holds(&board, stone, &smaller), "{:?} {}", template.kind, removed);
+                    board.set(removed.row, removed.col, Tile::Empty);
+                }
+
+                // And it's found on every edge
+                let edges = [Edge::Bottom, Edge::Top, Edge::Right, Edge::Left];
+                for (symmetry, edge) in Symmetry::ALL.into_iter().zip(edges) {
+                    let connections = Connections::find(&board.transformed(symmetry), symmetry.tile(Tile::Black)).unwrap();
+                    let found = connections.to_edge(0, edge).unwrap();
+                    assert_eq!(found.kind, template.kind);
+                    assert_eq!(found.carrier.len(), carrier.len());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn finds_bridges() {
+        // A stone on the top edge, bridged to one that's bridged to the bottom edge
+        let board = Board::from("..B.|....|.B..|....|");
+        let connections = Connections::find(&board, Tile::Black).unwrap();
+        assert_eq!(connections.groups, vec![vec![Coord::new(0, 2)], vec![Coord::new(2, 1)]]);
+        assert_eq!(connections.to_edge(0, Edge::Top).unwrap().kind, Kind::Adjacent);
+
+        let bridge = connections.between(1, 0).unwrap();
+        assert_eq!(bridge.kind, Kind::Bridge);
+        assert_eq!(bridge.carrier, vec![Coord::new(1, 1), Coord::new(1, 2)]);
+
+        let bottom = connections.to_edge(1, Edge::Bottom).unwrap();
+        assert_eq!(bottom.kind, Kind::Bridge);
+        assert_eq!(bottom.carrier, vec![Coord::new(3, 0), Coord::new(3, 1)]);
+
+        // Black has virtually won, and white has to break in between the stones or below them
+        assert_eq!(connections.winning_chain().unwrap().len(), 3);
+        let must_play = vec![Coord::new(1, 1), Coord::new(1, 2), Coord::new(3, 0), Coord::new(3, 1)];
+        assert_eq!(connections.must_play(), Some(must_play));
+
+        // Breaking into the bridge leaves it a single tile
+        let mut board = board;
+        board.set(1, 1, Tile::White);
+        let connections = Connections::find(&board, Tile::Black).unwrap();
+        assert_eq!(connections.between(0, 1), None);
+        assert_eq!(connections.winning_chain(), None);
+
+        // White's edges are found through the same templates
+        let board = Board::from("....|...W|....|....|");
+        let connections = Connections::find(&board, Tile::White).unwrap();
+        assert_eq!(connections.to_edge(0, Edge::Right).unwrap().kind, Kind::Adjacent);
+        assert_eq!(connections.to_edge(0, Edge::Left), None);
+        assert!(Connections::find(&board, Tile::Empty).is_none());
+    }
+}
@@ -0,0 +1,273 @@
+// Turns recorded games into training tensors, saved as NumPy `.npy` files that `numpy.load` reads
+// directly. Every position where a stone was placed becomes one sample:
+//
+// - `planes.npy`: uint8, (positions, 4, size, size). Black stones, white stones, empty tiles, and
+//   a plane of ones when black is to move (zeros when white is)
+// - `moves.npy`: int64, (positions,). The move that was played, as `row * size + col`
+// - `outcomes.npy`: int8, (positions,). 1 if the side to move went on to win, -1 if it lost
+//
+// Augmented copies of a position are written right after the original
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::board::{Board, Coord, Symmetry, Tile};
+use crate::game_data::{Action, GameRecord};
+
+pub const PLANES: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sample {
+    // PLANES * size * size values of 0 or 1
+    pub planes: Vec<u8>,
+    pub target: Coord,
+    pub outcome: i8,
+}
+
+// Which equivalent copies of each position to add
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Augment {
+    None,
+    // Also the board turned 180 degrees, doubling the data
+    Rotation,
+    // Also the transposed board with colors swapped, and its rotation, quadrupling the data
+    All,
+}
+
+impl Augment {
+    pub fn symmetries(self) -> &'static [Symmetry] {
+        match self {
+            Self::None => &Symmetry::ALL[..1],
+            Self::Rotation => &Symmetry::ALL[..2],
+            Self::All => &Symmetry::ALL,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    // Games that made it into the dataset
+    pub games: usize,
+    // Line number and reason for every game that didn't parse or didn't pass validation. Ex:
+    // (12231, "move 42 comes after the game ended")
+    pub skipped: Vec<(usize, String)>,
+    pub positions: usize,
+}
+
+// The positions of a valid game, leaving out the first `skip` moves. Those moves are still played
+// on the board, they just aren't used as samples. Swaps and resignations aren't samples either.
+// Each position is written once per symmetry, with the side to move and the move remapped
+pub fn samples(record: &GameRecord, size: u8, skip: usize, symmetries: &[Symmetry]) -> Vec<Sample> {
+    let mut board = Board::new(size);
+    let mut samples = Vec::new();
+
+    for (turn, &(color, action)) in record.moves.iter().enumerate() {
+        match action {
+            Action::Play(coord) => {
+                if turn >= skip {
+                    for &symmetry in symmetries {
+                        samples.push(Sample {
+                            planes: planes(&board.transformed(symmetry), symmetry.tile(color)),
+                            target: symmetry.coord(coord, size as usize),
+                            outcome: if color == record.winner { 1 } else { -1 },
+                        });
+                    }
+                }
+                board.set(coord.row, coord.col, color);
+            }
+            Action::Swap => if let Some(&(_, Action::Play(first))) = record.moves.first() {
+                board.set(first.row, first.col, Tile::Empty);
+                board.set(first.col, first.row, color);
+            },
+            Action::Resign => break,
+        }
+    }
+    samples
+}
+
+// How many samples `samples` would make of a valid game, without building them
+pub fn sample_count(record: &GameRecord, skip: usize, symmetries: &[Symmetry]) -> usize {
+    let played = record.moves.iter().enumerate()
+        .take_while(|(_, (_, action))| *action != Action::Resign)
+        .filter(|&(turn, (_, action))| turn >= skip && matches!(action, Action::Play(_)))
+        .count();
+    played * symmetries.len()
+}
+
+// One hot encoding of a position, plane by plane and row by row
+fn planes(board: &Board, to_move: Tile) -> Vec<u8> {
+    let size = board.size();
+    let mut planes = vec![0; PLANES * size * size];
+
+    for row in 0..size {
+        for col in 0..size {
+            let plane = match board.get(row, col) {
+                Some(Tile::Black) => 0,
+                Some(Tile::White) => 1,
+                _ => 2,
+            };
+            planes[(plane * size + row) * size + col] = 1;
+
+            if to_move == Tile::Black {
+                planes[(3 * size + row) * size + col] = 1;
+            }
+        }
+    }
+    planes
+}
+
+// Writes the three `.npy` files into `out_dir`, creating it if needed. Games with problems are
+// skipped rather than guessed at, and listed in the summary along with their first problem
+pub fn export(games: &Path, out_dir: &Path, size: u8, skip: usize, augment: Augment) -> io::Result<Summary> {
+    let symmetries = augment.symmetries();
+    let lines = crate::game_data::read_games(games)?;
+    let mut records: Vec<&GameRecord> = Vec::new();
+    let mut skipped = Vec::new();
+
+    for (number, line) in lines.iter().enumerate() {
+        match line {
+            Err(e) => skipped.push((number + 1, e.to_string())),
+            Ok(record) => match record.validate(size).first() {
+                Some(problem) => skipped.push((number + 1, problem.to_string())),
+                None => records.push(record),
+            },
+        }
+    }
+
+    // Counting first means the headers can be written up front and the samples streamed after
+    let positions = records.iter().map(|record| sample_count(record, skip, symmetries)).sum();
+
+    fs::create_dir_all(out_dir)?;
+    let mut planes = BufWriter::new(File::create(out_dir.join("planes.npy"))?);
+    let mut moves = BufWriter::new(File::create(out_dir.join("moves.npy"))?);
+    let mut outcomes = BufWriter::new(File::create(out_dir.join("outcomes.npy"))?);
+
+    write_npy_header(&mut planes, "|u1", &[positions, PLANES, size as usize, size as usize])?;
+    write_npy_header(&mut moves, "<i8", &[positions])?;
+    write_npy_header(&mut outcomes, "|i1", &[positions])?;
+
+    for record in &records {
+        for sample in samples(record, size, skip, symmetries) {
+            planes.write_all(&sample.planes)?;
+            moves.write_all(&((sample.target.row * size as usize + sample.target.col) as i64).to_le_bytes())?;
+            outcomes.write_all(&sample.outcome.to_le_bytes())?;
+        }
+    }
+    planes.flush()?;
+    moves.flush()?;
+    outcomes.flush()?;
+
+    Ok(Summary { games: records.len(), skipped, positions })
+}
+
+// Version 1.0 of the format: magic string, version, little endian header length, then a python
+// dict literal padded with spaces so that the data starts on a multiple of 64 bytes
+pub fn write_npy_header(out: &mut impl Write, descr: &str, shape: &[usize]) -> io::Result<()> {
+    let shape = match shape {
+        [length] => format!("({},)", length),
+        _ => format!("({})", shape.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+    let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape);
+
+    // 10 bytes of magic, version and length come before the dict, and it ends in a newline
+    let padding = 63 - (10 + header.len()) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    out.write_all(b"\x93NUMPY\x01\x00")?;
+    out.write_all(&(header.len() as u16).to_le_bytes())?;
+    out.write_all(header.as_bytes())
+}
+
+#[cfg(test)]
+mod dataset_testing {
+    use super::*;
+
+    #[test]
+    fn npy_headers() {
+        let mut out = Vec::new();
+        write_npy_header(&mut out, "|u1", &[7, 4, 10, 10]).unwrap();
+
+        assert_eq!(out.len() % 64, 0);
+        assert_eq!(&out[..8], b"\x93NUMPY\x01\x00");
+        assert_eq!(u16::from_le_bytes([out[8], out[9]]) as usize, out.len() - 10);
+
+        let header = std::str::from_utf8(&out[10..]).unwrap();
+        assert!(header.starts_with("{'descr': '|u1', 'fortran_order': False, 'shape': (7, 4, 10, 10), }"));
+        assert!(header.ends_with(" \n"));
+
+        let mut out = Vec::new();
+        write_npy_header(&mut out, "<i8", &[3]).unwrap();
+        assert!(std::str::from_utf8(&out[10..]).unwrap().contains("'shape': (3,)"));
+    }
+
+    #[test]
+    fn samples_skip_opening_moves() {
+        let record: GameRecord = "b a1,w a2,b b1,w b2,b c1,B".parse().unwrap();
+        let samples = samples(&record, 3, 2, Augment::None.symmetries());
+
+        assert_eq!(samples.len(), 3);
+        assert_eq!(sample_count(&record, 2, Augment::None.symmetries()), 3);
+        assert_eq!(samples.iter().map(|s| s.target).collect::<Vec<_>>(),
+            vec![Coord::new(1, 0), Coord::new(1, 1), Coord::new(2, 0)]);
+        assert_eq!(samples.iter().map(|s| s.outcome).collect::<Vec<_>>(), vec![1, -1, 1]);
+
+        // The skipped moves are still on the board: black on a1, white on a2, black to move
+        let planes = &samples[0].planes;
+        assert_eq!(&planes[0..9], &[1, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&planes[9..18], &[0, 1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&planes[18..27], &[0, 0, 1, 1, 1, 1, 1, 1, 1]);
+        assert_eq!(&planes[27..36], &[1; 9]);
+        assert_eq!(&samples[1].planes[27..36], &[0; 9]);
+    }
+
+    #[test]
+    fn samples_stop_at_resignation() {
+        let record: GameRecord = "b a2,w swap,b a1,w resign,B".parse().unwrap();
+        let samples = samples(&record, 3, 2, Augment::None.symmetries());
+
+        assert_eq!(samples.len(), 1);
+        assert_eq!(sample_count(&record, 2, Augment::None.symmetries()), 1);
+        // The swapped stone is white and on b1
+        assert_eq!(&samples[0].planes[9..18], &[0, 0, 0, 1, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn augmented_samples() {
+        let record: GameRecord = "b a1,w a2,b b1,w b2,b c1,B".parse().unwrap();
+        let samples = samples(&record, 3, 2, Augment::All.symmetries());
+
+        assert_eq!(samples.len(), 12);
+        assert_eq!(sample_count(&record, 2, Augment::All.symmetries()), 12);
+        assert_eq!(samples[..4].iter().map(|s| s.target).collect::<Vec<_>>(),
+            vec![Coord::new(1, 0), Coord::new(1, 2), Coord::new(0, 1), Coord::new(2, 1)]);
+        assert!(samples[..4].iter().all(|s| s.outcome == 1));
+
+        // Black on a1 and white on a2, turned around, then transposed with the colors swapped
+        assert_eq!(&samples[1].planes[0..18], &[0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 0]);
+        assert_eq!(&samples[2].planes[0..18], &[0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+        // After swapping colors it's white to move
+        assert_eq!(&samples[1].planes[27..36], &[1; 9]);
+        assert_eq!(&samples[2].planes[27..36], &[0; 9]);
+    }
+
+    #[test]
+    fn export_lists_skipped_games() {
+        let dir = std::env::temp_dir().join(format!("dataset_export_{}", std::process::id()));
+        let games = dir.join("games.txt");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&games, "b a1,w a2,b b1,w b2,b c1,B\nb a1,w a2,b b1,w b2,b c1,w resign,B\n\
+            b a1,w a1,B\n=======\n").unwrap();
+
+        let summary = export(&games, &dir, 3, 0, Augment::None).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(summary.games, 2);
+        assert_eq!(summary.positions, 10);
+        assert_eq!(summary.skipped, vec![
+            (3, "move 2 at a1 is already taken".to_string()),
+            (4, "git conflict marker".to_string()),
+        ]);
+    }
+}
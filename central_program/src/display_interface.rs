@@ -1,24 +1,20 @@
-mod board;
-
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyEvent, KeyCode, KeyModifiers},
+    event::{DisableMouseCapture, EnableMouseCapture, Event},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 
 use tui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Alignment, Constraint, Corner, Direction, Layout},
+    layout::{Constraint, Corner, Direction, Layout},
     style::{Color, Modifier, Style},
-    text::{Span, Spans, Text},
-    widgets::{Block, Borders, BorderType, List, ListItem, Paragraph},
+    text::Span,
+    widgets::{Block, Borders, BorderType, Gauge, List, ListItem, Paragraph},
     Frame, Terminal,
 };
-use board::{Board, Tile};
-use regex::Regex;
-use std::process::{self, Command, Stdio, Child};
-use std::io::{self, BufRead, BufReader, Write};
-use std::path::PathBuf;
+use sentience_validator::{Board, Coord, Message, Tile};
+use sentience_validator::connections::Connections;
+use std::io::{self, BufRead, Write};
 
 type Try<T> = Result<T, Box<dyn std::error::Error>>;
 
@@ -54,12 +50,23 @@ fn main() -> Try<()> {
     Ok(())
 }
 
+// Answers the central program on stdout
+fn reply(answer: impl std::fmt::Display) -> Try<()> {
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", answer)?;
+    stdout.flush()?;
+    Ok(())
+}
+
 #[derive(Debug)]
 struct App {
     board: Board,
     size: usize,
     messages: Vec<String>,
     color: Tile,
+    // Empty tiles holding a virtual connection together, with the color they belong to. Only
+    // found again when the board changes, rather than on every frame
+    carriers: Vec<(Tile, Coord)>,
 }
 
 impl App {
@@ -68,46 +75,48 @@ impl App {
 
         Self {
             board: Board::new(default_size),
-            size: default_size,
+            size: default_size as usize,
             messages: vec!["Starting game...".to_string(), "Starting game...".to_string()],
             color,
+            carriers: Vec::new(),
         }
     }
 
     fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Try<()> {
-        let re_init = Regex::new(r"init_board (\d+)").unwrap();
-        let re_make_move = Regex::new(r"make_move").unwrap();
-        let re_seto = Regex::new(r"seto ([a-z]\d)").unwrap();
-        let re_sety = Regex::new(r"sety ([a-z]\d)").unwrap();
-        let re_unset = Regex::new(r"unset ([a-z]\d)").unwrap();
-        let re_quit = Regex::new(r"quit").unwrap();
-
         let stdin = io::stdin();
-        let mut lines = stdin.lock().lines().map(|s| s.expect("Failed to read line from stdin"));
+        let lines = stdin.lock().lines().map(|s| s.expect("Failed to read line from stdin"));
 
         terminal.draw(|f| self.tui(f))?;
         self.get_next_click(terminal).expect("Failed to get crossterm event");
 
         for ref line in lines {
-            if re_init.is_match(line) {
-                let caps = re_init.captures(line).unwrap();
-                self.size = caps.get(1).unwrap().as_str().parse()?;
-                self.board = Board::new(self.size as u8);
-                self.messages.push(format!("Created new board of size {s}x{s}", s = self.size));
-            } else if re_make_move.is_match(line) {
-                let mut stdout = io::stdout().lock();
-                //let reply = self.get_next_click().expect("Failed to get crossterm event");
-                //writeln!(&mut stdout, "{reply}")?;
-            } else if re_seto.is_match(line) {
-                todo!();
-            } else if re_sety.is_match(line) {
-                todo!();
-            } else if re_unset.is_match(line) {
-                todo!();
-            } else if re_quit.is_match(line) {
-                return Ok(());
-            } else {
-                panic!("Unrecognized command from central: `{line}`");
+            match line.parse::<Message>() {
+                Ok(Message::InitBoard(size)) => {
+                    self.size = size as usize;
+                    self.board = Board::new(size);
+                    self.carriers.clear();
+                    self.messages.push(format!("Created new board of size {s}x{s}", s = self.size));
+                }
+                Ok(Message::MakeMove) => {
+                    //let reply = self.get_next_click().expect("Failed to get crossterm event");
+                    //writeln!(io::stdout().lock(), "{reply}")?;
+                }
+                Ok(Message::ShowBoard) => reply(self.board.to_compressed())?,
+                Ok(Message::CheckWin) => reply(match self.board.has_win() {
+                    Tile::Empty => 0,
+                    winner if winner == self.color => 1,
+                    _ => -1,
+                })?,
+                Ok(Message::SetO(coord)) => self.set(coord, self.color.opponent()),
+                Ok(Message::SetY(coord)) => self.set(coord, self.color),
+                Ok(Message::Unset(coord)) => self.set(coord, Tile::Empty),
+                Ok(Message::Swap) => {
+                    self.color = self.color.opponent();
+                    self.messages.push(format!("Swapped, now playing {}", self.color));
+                }
+                Ok(Message::TimeLeft(_)) => (),
+                Ok(Message::Quit) => return Ok(()),
+                Err(e) => panic!("Unrecognized command from central: `{line}`: {e}"),
             }
 
             terminal.draw(|f| self.tui(f))?;
@@ -116,6 +125,15 @@ impl App {
         Ok(())
     }
 
+    fn set(&mut self, coord: Coord, tile: Tile) {
+        self.board.set(coord.row, coord.col, tile);
+        self.carriers = [Tile::Black, Tile::White].into_iter()
+            .filter_map(|color| Connections::find(&self.board, color))
+            .flat_map(|Connections { color, connections, .. }| connections.into_iter()
+                .flat_map(move |connection| connection.carrier.into_iter().map(move |c| (color, c))))
+            .collect();
+    }
+
     fn get_next_click<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Try<String> {
         loop {
             if let Event::Mouse(mouse) = crossterm::event::read()? {
@@ -160,21 +178,40 @@ impl App {
 
         let board_rows = Layout::default()
             .direction(Direction::Vertical)
-            .constraints(vec![Constraint::Length(3); self.size + 1].as_ref())
+            .constraints(vec![Constraint::Length(3); self.size + 1])
             .split(horizontal_board[1]);
 
         let tile_block = Block::default()
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded);
 
-        for i in 0..self.size {
+        let winning_path = self.board.winning_path(self.board.has_win()).unwrap_or_default();
+
+        for (i, &row) in board_rows.iter().enumerate().take(self.size) {
             let cols = Layout::default()
                 .direction(Direction::Horizontal)
-                .constraints(vec![Constraint::Length(5); self.size].as_ref())
-                .split(board_rows[i]);
-
-            for mut col in cols {
-                let bg = Span::styled("   ", BG_WHITE);
+                .constraints(vec![Constraint::Length(5); self.size])
+                .split(row);
+
+            for (j, mut col) in cols.into_iter().enumerate() {
+                let coord = Coord::new(i, j);
+                // Carrier tiles are marked in the color they belong to
+                let carrier = self.carriers.iter().find(|(_, c)| *c == coord).map(|(color, _)| *color);
+                let style = match (self.board.get(i, j), carrier) {
+                    (Some(Tile::Black), _) => BG_BLACK,
+                    (Some(Tile::White), _) => BG_WHITE,
+                    (_, Some(Tile::Black)) => Style::default().fg(Color::Blue),
+                    (_, Some(Tile::White)) => Style::default().fg(Color::Green),
+                    _ => Style::default(),
+                };
+                let content = if winning_path.contains(&coord) {
+                    " * "
+                } else if carrier.is_some() {
+                    " + "
+                } else {
+                    "   "
+                };
+                let bg = Span::styled(content, style);
 
                 col.x += 3 * i as u16;
                 //col.y -= i as u16;
@@ -183,7 +220,7 @@ impl App {
             }
         }
 
-        let mut col_labels: Vec<_> = (1..=(self.size + 1)).map(|n| format!("    {n}")).collect();
+        let col_labels: Vec<_> = (1..=(self.size + 1)).map(|n| format!("    {n}")).collect();
         eprintln!("Col length: {}", col_labels.len());
         eprintln!("Should be: {}", self.size);
 
@@ -211,10 +248,33 @@ impl App {
                 .collect::<Vec<ListItem>>())
             .start_corner(Corner::BottomLeft);
 
+        let right_side = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(1)].as_ref())
+            .split(outer[1]);
+
+        f.render_widget(self.advantage_bar(), right_side[0]);
         f.render_widget(
             list.block(Block::default().borders(Borders::ALL).title("Play Log")),
-            outer[1]);
+            right_side[1]);
 
         //f.render_widget(Block::default().borders(Borders::ALL), outer[0]);
     }
+
+    // Black's share of the bar grows with black's resistance evaluation, squashed into 0..1
+    fn advantage_bar(&self) -> Gauge<'static> {
+        let advantage = self.board.evaluate(Tile::Black);
+        let label = match advantage {
+            a if a == f64::INFINITY => "Black wins".to_string(),
+            a if a == f64::NEG_INFINITY => "White wins".to_string(),
+            a if a >= 0.0 => format!("Black +{:.2}", a),
+            a => format!("White +{:.2}", -a),
+        };
+
+        Gauge::default()
+            .block(Block::default().borders(Borders::ALL).title("Advantage"))
+            .gauge_style(Style::default().fg(Color::Blue).bg(Color::Green))
+            .ratio(1.0 / (1.0 + (-advantage).exp()))
+            .label(label)
+    }
 }
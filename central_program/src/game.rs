@@ -0,0 +1,552 @@
+// A game between two bot processes, refereed on the central board
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::board::{Board, Coord, Move, MoveError, Symmetry, Tile};
+use crate::bot::{Bot, Limits, NoReply, StderrLog, Usage};
+use crate::game_data::{Action, GameRecord};
+use crate::protocol::Message;
+use crate::units::{parse_duration, parse_size};
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct TimeControl {
+    /// Longest a bot may think about a single move. Ex: 120s, 1.5m, 500ms
+    #[clap(long, value_parser = parse_duration)]
+    pub move_time: Option<Duration>,
+    /// Thinking time each bot gets for the whole game, like a chess clock
+    #[clap(long, value_parser = parse_duration)]
+    pub game_time: Option<Duration>,
+    /// Time added to a bot's game clock after each of its moves
+    #[clap(long, value_parser = parse_duration, default_value = "0s")]
+    pub increment: Duration,
+    /// Send `time_left {ms}` to the bot before every `make_move`. Only use this if both bots
+    /// understand the message
+    #[clap(long, action)]
+    pub send_time_left: bool,
+}
+
+impl TimeControl {
+    // How long the bot may take for its next move, if there's a limit at all
+    pub fn budget(&self, bot: &Bot) -> Option<Duration> {
+        match (self.move_time, bot.clock) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+// How long a bot gets to answer `show_board` and `check_win` in strict mode
+const SYNC_TIMEOUT: Duration = Duration::from_secs(10);
+
+// What the referee does besides keeping time
+#[derive(clap::Args, Debug, Clone)]
+pub struct Referee {
+    /// Append every finished game to this file, in the format of training_data/game_data.txt
+    #[clap(long)]
+    pub record: Option<PathBuf>,
+    /// After every move, ask both bots for their board with `show_board`. A bot whose board
+    /// doesn't match the central one forfeits
+    #[clap(long, action)]
+    pub strict: bool,
+    /// Also ask both bots `check_win` after every move. Implies --strict
+    #[clap(long, action)]
+    pub strict_check_win: bool,
+    /// How a swap on the second move works
+    #[clap(long, value_enum, default_value = "color")]
+    pub swap_rule: SwapRule,
+    /// Write each bot's stderr to a file of its own in this directory for every game, instead of
+    /// passing it through. The files are named in the game's record
+    #[clap(long)]
+    pub stderr_dir: Option<PathBuf>,
+    /// Most stderr kept per bot per game. Ex: 500k, 10M
+    #[clap(long, value_parser = parse_size, default_value = "1M")]
+    pub stderr_cap: u64,
+    #[clap(flatten)]
+    pub limits: Limits,
+}
+
+impl Default for Referee {
+    fn default() -> Self {
+        Self {
+            record: None,
+            strict: false,
+            strict_check_win: false,
+            swap_rule: SwapRule::default(),
+            stderr_dir: None,
+            stderr_cap: 1 << 20,
+            limits: Limits::default(),
+        }
+    }
+}
+
+impl Referee {
+    // Log files for a new game's black and white bots, if stderr is being kept. Ex:
+    // "logs/1700000000-4242-3-black.log", for the third game of controller process 4242
+    fn stderr_logs(&self) -> Option<[StderrLog; 2]> {
+        static GAMES: AtomicUsize = AtomicUsize::new(1);
+
+        let dir = self.stderr_dir.as_ref()?;
+        let started = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let game = format!("{}-{}-{}", started, process::id(), GAMES.fetch_add(1, Ordering::Relaxed));
+        let log = |color| StderrLog { path: dir.join(format!("{}-{}.log", game, color)), cap: self.stderr_cap };
+
+        Some([log("black"), log("white")])
+    }
+}
+
+// The two conventions for the pie rule. They're the same game, transposed
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SwapRule {
+    /// The players swap colors and the board stays, as bots are told with `swap`
+    #[default]
+    Color,
+    /// The first stone moves to its mirror image across the long diagonal and becomes white's.
+    /// Both bots keep their colors
+    Stone,
+}
+
+// Why a game came to an end
+#[derive(Debug, Clone, PartialEq)]
+pub enum EndReason {
+    Connection,
+    // A move that can't be played, such as one on a taken tile
+    IllegalMove(MoveError),
+    // A reply that isn't a move at all, such as an empty line
+    MalformedReply(MoveError),
+    Timeout,
+    // The bot closed its stdout, usually by exiting. Says how it exited and what it last wrote
+    // to stderr
+    Crash(String),
+    // A bot's board didn't match the central one in strict mode. Says where they differ
+    Desync(String),
+    // A bot couldn't even be started
+    Error,
+}
+
+impl EndReason {
+    // What went wrong, for the reasons that have more to say than their name
+    pub fn details(&self) -> Option<String> {
+        match self {
+            Self::IllegalMove(e) | Self::MalformedReply(e) => Some(e.to_string()),
+            Self::Crash(report) | Self::Desync(report) => Some(report.clone()),
+            Self::Connection | Self::Timeout | Self::Error => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameResult {
+    // The color the winning bot started the game with, which is the other one on the board after
+    // a color swap
+    pub winner: Tile,
+    pub reason: EndReason,
+    pub moves: usize,
+    // The chain of stones that won a game by connection, on the central board. Empty otherwise
+    pub path: Vec<Coord>,
+    // What the bots that started as black and white used, once they've been shut down
+    pub usage: [Option<Usage>; 2],
+}
+
+impl GameResult {
+    // Exit code for the headless `play` command
+    pub fn exit_code(&self) -> i32 {
+        match (&self.reason, self.winner) {
+            (EndReason::Connection, Tile::Black) => 0,
+            (EndReason::Connection, Tile::White) => 1,
+            _ => 2,
+        }
+    }
+}
+
+// Machine readable summary, with the winning path of a connection and the bots' usage when it's
+// known. Ex: "winner=black reason=connection moves=31 path=a1,b1,c1 black_cpu_ms=1520 ..."
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.reason {
+            EndReason::Connection => "connection",
+            EndReason::IllegalMove(_) => "illegal_move",
+            EndReason::MalformedReply(_) => "malformed_reply",
+            EndReason::Timeout => "timeout",
+            EndReason::Crash(_) => "crash",
+            EndReason::Desync(_) => "desync",
+            EndReason::Error => "error",
+        };
+        let winner = match self.winner {
+            Tile::Empty => "none".to_string(),
+            color => color.to_string().to_lowercase(),
+        };
+        write!(f, "winner={} reason={} moves={}", winner, reason, self.moves)?;
+
+        if !self.path.is_empty() {
+            let path = self.path.iter().map(|coord| coord.to_string()).collect::<Vec<_>>();
+            write!(f, " path={}", path.join(","))?;
+        }
+
+        for (color, usage) in ["black", "white"].iter().zip(&self.usage) {
+            if let Some(usage) = usage {
+                write!(f, " {}_cpu_ms={} {}_peak_rss_kb={}", color, usage.cpu_time.as_millis(), color,
+                    usage.peak_rss)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// A game in progress between two bot processes
+pub struct Game {
+    pub board: Board,
+    pub black: Bot,
+    pub white: Bot,
+    time: TimeControl,
+    is_black_turn: bool,
+    // The bots have swapped colors, so `black` is the bot that started as white
+    swapped: bool,
+    moves: usize,
+    history: Vec<(Tile, Action)>,
+    // Where the black and white bots' stderr is going, if not to ours
+    logs: Option<[PathBuf; 2]>,
+    // Print every move as it's played
+    pub verbose: bool,
+    pub referee: Referee,
+}
+
+impl Game {
+    // Starts up both bots. If either fails, the other one wins by default
+    pub fn start(size: u8, black_path: &Path, white_path: &Path, time: TimeControl, referee: Referee)
+        -> Result<Self, Box<GameResult>>
+    {
+        let logs = referee.stderr_logs();
+        if let Some(dir) = &referee.stderr_dir {
+            if let Err(e) = fs::create_dir_all(dir) {
+                eprintln!("Failed to create {}: {}", dir.display(), e);
+            }
+        }
+        let [black_log, white_log] = match &logs {
+            Some([black, white]) => [Some(black), Some(white)],
+            None => [None, None],
+        };
+
+        let limits = &referee.limits;
+        match (Bot::spawn(black_path, "black", black_log, limits), Bot::spawn(white_path, "white", white_log, limits)) {
+            (Ok(black), Ok(white)) => {
+                let mut game = Self::new(size, black, white, time);
+                game.logs = logs.map(|logs| logs.map(|log| log.path));
+                game.referee = referee;
+                Ok(game)
+            }
+            (black, white) => {
+                let winner = match (&black, &white) {
+                    (Ok(_), _) => Tile::Black,
+                    (_, Ok(_)) => Tile::White,
+                    _ => Tile::Empty,
+                };
+
+                for (path, bot) in [(black_path, black), (white_path, white)] {
+                    match bot {
+                        Ok(mut bot) => bot.shutdown(),
+                        Err(e) => eprintln!("Failed to startup bot {}: {}", path.display(), e),
+                    }
+                }
+                Err(Box::new(GameResult { winner, reason: EndReason::Error, moves: 0, path: Vec::new(), usage: [None, None] }))
+            }
+        }
+    }
+
+    pub fn new(size: u8, mut black: Bot, mut white: Bot, time: TimeControl) -> Self {
+        init_board(size, &mut black, &mut white);
+        black.clock = time.game_time;
+        white.clock = time.game_time;
+
+        Self {
+            board: Board::new(size),
+            black,
+            white,
+            time,
+            is_black_turn: true,
+            swapped: false,
+            moves: 0,
+            history: Vec::new(),
+            logs: None,
+            verbose: true,
+            referee: Referee::default(),
+        }
+    }
+
+    // Plays a single turn. Returns the result once the game is over
+    pub fn step(&mut self) -> Option<GameResult> {
+        let turn_color = if self.is_black_turn { Tile::Black } else { Tile::White };
+        let played = self.play_turn();
+        self.moves += 1;
+
+        self.history.push((turn_color, match played {
+            Ok(Move::Place(coord)) => Action::Play(coord),
+            Ok(Move::Swap) => Action::Swap,
+            Err(_) => Action::Resign,
+        }));
+
+        let mut result = match played {
+            Err(reason) => Some(GameResult {
+                winner: self.seat(turn_color.opponent()),
+                reason,
+                moves: self.moves,
+                path: Vec::new(),
+                usage: [None, None],
+            }),
+            Ok(_) if self.board.has_win() != Tile::Empty => Some(GameResult {
+                winner: self.seat(self.board.has_win()),
+                reason: EndReason::Connection,
+                moves: self.moves,
+                path: self.board.winning_path(self.board.has_win()).unwrap_or_default(),
+                usage: [None, None],
+            }),
+            Ok(Move::Swap) if self.referee.swap_rule == SwapRule::Color => {
+                std::mem::swap(&mut self.black, &mut self.white);
+                self.swapped = true;
+                None
+            }
+            Ok(Move::Swap) => {
+                self.is_black_turn = true;
+                None
+            }
+            Ok(Move::Place(_)) => {
+                self.is_black_turn = !self.is_black_turn;
+                None
+            }
+        };
+
+        // A finished game has nothing left to check, and would only turn a win into a desync
+        if result.is_none() && (self.referee.strict || self.referee.strict_check_win) {
+            result = self.check_sync();
+        }
+
+        // Games without a winner can't be written in the record format
+        let decided = result.as_ref().filter(|result| result.winner != Tile::Empty);
+        if let (Some(result), Some(path)) = (decided, &self.referee.record) {
+            if let Err(e) = self.record(result.winner).append_to(path) {
+                eprintln!("Failed to record game to {}: {}", path.display(), e);
+            }
+        }
+        result
+    }
+
+    // Compares both bots' boards with the central one, and their answer to `check_win` if asked
+    // to. The game ends on the first difference, which loses it for the bot at fault, or for
+    // nobody if both are. A single bot at fault resigns in the game's history, even if it isn't
+    // its turn
+    fn check_sync(&mut self) -> Option<GameResult> {
+        let check_win = self.referee.strict_check_win;
+        let mut faults = Vec::new();
+
+        for (bot, color) in [(&mut self.black, Tile::Black), (&mut self.white, Tile::White)] {
+            if let Some(fault) = sync_fault(&self.board, bot, color, check_win) {
+                if self.verbose {
+                    println!("{} forfeits, out of sync with the central board: {}", color, fault);
+                }
+                faults.push((color, fault));
+            }
+        }
+
+        let winner = match faults.as_slice() {
+            [] => return None,
+            [(color, _)] => {
+                self.history.push((*color, Action::Resign));
+                color.opponent()
+            }
+            _ => Tile::Empty,
+        };
+        let report = faults.iter()
+            .map(|(color, fault)| format!("{}: {}", color, fault))
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        Some(GameResult {
+            winner: self.seat(winner),
+            reason: EndReason::Desync(report),
+            moves: self.moves,
+            path: Vec::new(),
+            usage: [None, None],
+        })
+    }
+
+    // Which bot is playing a color, by the color it started with
+    fn seat(&self, color: Tile) -> Tile {
+        if self.swapped { color.opponent() } else { color }
+    }
+
+    // The game in the record format, which writes swaps in the stone-swap convention. After a
+    // color swap that's the same game transposed, with the colors swapped from the swap onwards
+    fn record(&self, winner: Tile) -> GameRecord {
+        let mut moves = self.history.clone();
+
+        if self.swapped {
+            for (color, action) in moves.iter_mut().skip(2) {
+                *color = color.opponent();
+                if let Action::Play(coord) = action {
+                    *coord = Symmetry::ColorSwap.coord(*coord, self.board.size());
+                }
+            }
+        }
+        GameRecord { moves, winner, logs: self.logs.clone() }
+    }
+
+    // Keeps playing until somebody wins or forfeits
+    pub fn play_out(&mut self) -> GameResult {
+        loop {
+            if let Some(result) = self.step() {
+                return result;
+            }
+        }
+    }
+
+    // Asks the current bot for a move. A bot that crashes, replies late or replies with anything
+    // but a legal move forfeits the game
+    fn play_turn(&mut self) -> Result<Move, EndReason> {
+        let (this_turn_bot, next_turn_bot, this_turn_color) = if self.is_black_turn {
+            (&mut self.black, &mut self.white, Tile::Black)
+        } else {
+            (&mut self.white, &mut self.black, Tile::White)
+        };
+        let time = &self.time;
+
+        let budget = time.budget(this_turn_bot);
+        if let (true, Some(budget)) = (time.send_time_left, budget) {
+            this_turn_bot.send(Message::TimeLeft(budget));
+        }
+
+        let start = Instant::now();
+        let response = this_turn_bot.request(Message::MakeMove, budget);
+        let elapsed = start.elapsed();
+
+        let response = match response {
+            Ok(response) if budget.is_none_or(|budget| elapsed <= budget) => response,
+            Err(NoReply::Closed) => {
+                let report = this_turn_bot.crash_report();
+                if self.verbose {
+                    println!("{} forfeits by crashing: {}", this_turn_color, report);
+                }
+                return Err(EndReason::Crash(report));
+            }
+            _ => {
+                if self.verbose {
+                    println!("{} forfeits on time after {:.3}s", this_turn_color, elapsed.as_secs_f64());
+                }
+                return Err(EndReason::Timeout);
+            }
+        };
+        this_turn_bot.clock = this_turn_bot.clock.map(|clock| clock - elapsed + time.increment);
+
+        let mv = response.trim();
+        let checked = match self.board.check_move(mv) {
+            Ok(Move::Swap) if self.moves != 1 => Err(MoveError::MisplacedSwap),
+            checked => checked,
+        };
+
+        match checked {
+            Ok(Move::Swap) => {
+                if self.verbose {
+                    println!("{}'s move: swap", this_turn_color);
+                }
+                match self.referee.swap_rule {
+                    SwapRule::Color => next_turn_bot.send(Message::Swap),
+                    // The swapper took `swap` to mean it changed colors, so it's swapped back
+                    SwapRule::Stone => {
+                        let first = self.board.first_stone().unwrap();
+                        self.board.set_move(mv, this_turn_color).unwrap();
+                        let moved = Symmetry::ColorSwap.coord(first, self.board.size());
+
+                        this_turn_bot.send(Message::Swap);
+                        this_turn_bot.send(Message::Unset(first));
+                        this_turn_bot.send(Message::SetY(moved));
+                        next_turn_bot.send(Message::Unset(first));
+                        next_turn_bot.send(Message::SetO(moved));
+                    }
+                }
+                Ok(Move::Swap)
+            }
+            Ok(Move::Place(coord)) => {
+                self.board.set(coord.row, coord.col, this_turn_color);
+                if self.verbose {
+                    println!("{}'s move: {}", this_turn_color, coord);
+                }
+                next_turn_bot.send(Message::SetO(coord));
+                Ok(Move::Place(coord))
+            }
+            Err(e @ (MoveError::Empty | MoveError::BadLetter(_) | MoveError::MissingNumber
+                | MoveError::TrailingGarbage(_))) =>
+            {
+                if self.verbose {
+                    println!("{} forfeits with malformed reply `{}`: {}", this_turn_color, mv.escape_debug(), e);
+                }
+                Err(EndReason::MalformedReply(e))
+            }
+            Err(e) => {
+                if self.verbose {
+                    println!("{} forfeits with illegal move `{}`: {}", this_turn_color, mv.escape_debug(), e);
+                }
+                Err(EndReason::IllegalMove(e))
+            }
+        }
+    }
+
+    // Tells both bots the game is over and waits for them to exit. Returns what the bots that
+    // started as black and white used
+    pub fn shutdown(&mut self) -> [Option<Usage>; 2] {
+        self.black.shutdown();
+        self.white.shutdown();
+
+        let usage = [self.black.usage(), self.white.usage()];
+        if self.swapped { [usage[1], usage[0]] } else { usage }
+    }
+}
+
+// What's wrong with a bot's idea of the game, if anything. Ex: "board differs at a1 (central
+// Black, bot Empty)"
+fn sync_fault(board: &Board, bot: &mut Bot, color: Tile, check_win: bool) -> Option<String> {
+    let reply = match bot.request(Message::ShowBoard, Some(SYNC_TIMEOUT)) {
+        Ok(reply) => reply,
+        Err(NoReply::Timeout) => return Some("no answer to `show_board`".to_string()),
+        Err(NoReply::Closed) => return Some(format!("crashed ({})", bot.crash_report())),
+    };
+    let theirs = match Board::parse(&reply) {
+        Ok(theirs) => theirs,
+        Err(e) => return Some(format!("`show_board` replied `{}`: {}", reply.trim().escape_debug(), e)),
+    };
+
+    if theirs.size() != board.size() {
+        return Some(format!("{0}x{0} board instead of {1}x{1}", theirs.size(), board.size()));
+    }
+    let diff = board.diff(&theirs);
+    if !diff.is_empty() {
+        let tiles: Vec<String> = diff.iter()
+            .map(|c| format!("{} (central {}, bot {})", c, board.get(c.row, c.col).unwrap(),
+                theirs.get(c.row, c.col).unwrap()))
+            .collect();
+        return Some(format!("board differs at {}", tiles.join(", ")));
+    }
+
+    if check_win {
+        let expected = match board.has_win() {
+            Tile::Empty => "0",
+            winner if winner == color => "1",
+            _ => "-1",
+        };
+        match bot.request(Message::CheckWin, Some(SYNC_TIMEOUT)) {
+            Err(NoReply::Timeout) => return Some("no answer to `check_win`".to_string()),
+            Err(NoReply::Closed) => return Some(format!("crashed ({})", bot.crash_report())),
+            Ok(reply) if reply.trim() != expected => {
+                return Some(format!("`check_win` replied `{}` instead of {}", reply.trim().escape_debug(), expected));
+            }
+            Ok(_) => (),
+        }
+    }
+    None
+}
+
+fn init_board(size: u8, black: &mut Bot, white: &mut Bot) {
+    black.send(Message::InitBoard(size));
+    white.send(Message::InitBoard(size));
+}
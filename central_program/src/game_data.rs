@@ -0,0 +1,333 @@
+// Games in the format of `training_data/game_data.txt`, one per line:
+// `<color> <move>,<color> <move>,...,<color> <move>,<winner>`
+// Ex: "b a1,w b2,b c3,w resign,B"
+// Games whose bots' stderr was kept end with a comment naming the files.
+// Ex: "b a1,w b2,b c3,w resign,B # stderr black=logs/1-black.log white=logs/1-white.log"
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::board::{Board, Coord, MoveError, Tile, MAX_SIZE};
+
+// Anything a player can do on their turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Play(Coord),
+    Swap,
+    // Forfeits, such as illegal moves or running out of time, are written as resignations
+    Resign,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+    pub moves: Vec<(Tile, Action)>,
+    pub winner: Tile,
+    // Files holding the black and white bots' stderr for this game
+    pub logs: Option<[PathBuf; 2]>,
+}
+
+impl GameRecord {
+    // Adds the game as a new line at the end of the file
+    pub fn append_to(&self, path: &Path) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", self)
+    }
+
+    // Replays the game on an empty board, returning everything wrong with it. A swap moves the
+    // first stone to its mirror image across the long diagonal and gives it to white
+    pub fn validate(&self, size: u8) -> Vec<Problem> {
+        let mut board = Board::new(size);
+        let mut problems = Vec::new();
+        let mut resigned = None;
+        let mut ended = false;
+
+        for (turn, &(color, action)) in self.moves.iter().enumerate() {
+            let expected = if turn % 2 == 0 { Tile::Black } else { Tile::White };
+            // Either side may resign at any time, like a bot forfeiting for a desync in strict mode
+            if color != expected && action != Action::Resign {
+                problems.push(Problem::WrongTurn { turn, expected });
+            }
+            // The loser resigning right after a connection is how the Wolve games end, as in
+            // `b d1,w resign,B`, so that's not an extra move
+            let won = board.has_win();
+            let concedes = action == Action::Resign && won != Tile::Empty && won != color
+                && turn + 1 == self.moves.len();
+
+            // Only the first extra move is reported, the rest follow from it
+            if !ended && !concedes && (resigned.is_some() || won != Tile::Empty) {
+                problems.push(Problem::MoveAfterEnd { turn });
+                ended = true;
+            }
+
+            match action {
+                Action::Play(coord) if coord.row >= size as usize || coord.col >= size as usize => {
+                    problems.push(Problem::OffBoard { turn, coord });
+                }
+                Action::Play(coord) if board.get(coord.row, coord.col) != Some(Tile::Empty) => {
+                    problems.push(Problem::Occupied { turn, coord });
+                }
+                Action::Play(coord) => board.set(coord.row, coord.col, color),
+                Action::Swap => match self.moves.first() {
+                    Some(&(_, Action::Play(first))) if turn == 1 => {
+                        board.set(first.row, first.col, Tile::Empty);
+                        board.set(first.col, first.row, color);
+                    }
+                    _ => problems.push(Problem::MisplacedSwap { turn }),
+                },
+                Action::Resign => resigned = Some(color),
+            }
+        }
+
+        let actual = match resigned {
+            Some(Tile::Black) => Tile::White,
+            Some(Tile::White) => Tile::Black,
+            _ => board.has_win(),
+        };
+        if actual != self.winner {
+            problems.push(Problem::WinnerMismatch { declared: self.winner, actual });
+        }
+        problems
+    }
+}
+
+// Reads every line of a game file. Lines that aren't games are kept as errors, so that they can
+// be reported with their line number
+pub fn read_games(path: &Path) -> io::Result<Vec<Result<GameRecord, ParseError>>> {
+    Ok(fs::read_to_string(path)?.lines().map(str::parse).collect())
+}
+
+impl FromStr for GameRecord {
+    type Err = ParseError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        if ["<<<<<<<", "=======", ">>>>>>>"].iter().any(|marker| line.starts_with(marker)) {
+            return Err(ParseError::ConflictMarker);
+        }
+
+        let (line, logs) = match line.trim_end().split_once(" # ") {
+            Some((game, comment)) => {
+                let logs = comment.strip_prefix("stderr black=")
+                    .and_then(|paths| paths.split_once(" white="))
+                    .ok_or_else(|| ParseError::BadComment(comment.to_string()))?;
+                (game, Some([PathBuf::from(logs.0), PathBuf::from(logs.1)]))
+            }
+            None => (line.trim_end(), None),
+        };
+
+        let mut fields: Vec<&str> = line.split(',').collect();
+        let winner = match fields.pop() {
+            Some("B") => Tile::Black,
+            Some("W") => Tile::White,
+            Some("") | None => return Err(ParseError::MissingWinner),
+            Some(other) => return Err(ParseError::BadWinner(other.to_string())),
+        };
+
+        let moves = fields.into_iter()
+            .map(|field| {
+                let (color, action) = field.split_once(' ')
+                    .ok_or_else(|| ParseError::BadField(field.to_string()))?;
+
+                let color = match color {
+                    "b" => Tile::Black,
+                    "w" => Tile::White,
+                    _ => return Err(ParseError::BadColor(color.to_string())),
+                };
+                let action = match action {
+                    "swap" => Action::Swap,
+                    "resign" => Action::Resign,
+                    // Checked against the real board size while replaying
+                    _ => Action::Play(Coord::parse(action, MAX_SIZE)
+                        .map_err(|e| ParseError::BadMove(action.to_string(), e))?),
+                };
+                Ok((color, action))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { moves, winner, logs })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    // Left over from a botched git merge. Ex: "<<<<<<< HEAD"
+    ConflictMarker,
+    MissingWinner,
+    BadWinner(String),
+    // A move that isn't a color and an action separated by a space
+    BadField(String),
+    BadColor(String),
+    BadMove(String, MoveError),
+    // Anything after ` # ` that doesn't name the stderr logs
+    BadComment(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ConflictMarker => write!(f, "git conflict marker"),
+            Self::MissingWinner => write!(f, "missing winner at the end of the line"),
+            Self::BadWinner(s) => write!(f, "winner `{}` is neither B nor W", s.escape_debug()),
+            Self::BadField(s) => write!(f, "`{}` is not of the form `<color> <move>`", s.escape_debug()),
+            Self::BadColor(s) => write!(f, "color `{}` is neither b nor w", s.escape_debug()),
+            Self::BadMove(s, e) => write!(f, "bad move `{}`: {}", s.escape_debug(), e),
+            Self::BadComment(s) => write!(f, "comment `{}` doesn't name the stderr logs", s.escape_debug()),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Something wrong with a game that parsed fine. Turns count from 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    WrongTurn { turn: usize, expected: Tile },
+    OffBoard { turn: usize, coord: Coord },
+    Occupied { turn: usize, coord: Coord },
+    // Swapping is only allowed as the second move, right after a stone was placed
+    MisplacedSwap { turn: usize },
+    // The game was already won or resigned. Only the first such move is reported
+    MoveAfterEnd { turn: usize },
+    WinnerMismatch { declared: Tile, actual: Tile },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongTurn { turn, expected } =>
+                write!(f, "move {} should have been played by {:?}", turn + 1, expected),
+            Self::OffBoard { turn, coord } => write!(f, "move {} at {} is off the board", turn + 1, coord),
+            Self::Occupied { turn, coord } => write!(f, "move {} at {} is already taken", turn + 1, coord),
+            Self::MisplacedSwap { turn } => write!(f, "move {} swaps, which is only allowed as move 2", turn + 1),
+            Self::MoveAfterEnd { turn } => write!(f, "move {} comes after the game ended", turn + 1),
+            Self::WinnerMismatch { declared, actual } =>
+                write!(f, "declared winner is {:?} but the game says {:?}", declared, actual),
+        }
+    }
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (color, action) in &self.moves {
+            let color = match color {
+                Tile::Black => 'b',
+                Tile::White => 'w',
+                Tile::Empty => '?',
+            };
+
+            match action {
+                Action::Play(coord) => write!(f, "{} {},", color, coord)?,
+                Action::Swap => write!(f, "{} swap,", color)?,
+                Action::Resign => write!(f, "{} resign,", color)?,
+            }
+        }
+
+        match self.winner {
+            Tile::Black => write!(f, "B")?,
+            Tile::White => write!(f, "W")?,
+            Tile::Empty => write!(f, "?")?,
+        }
+
+        match &self.logs {
+            Some([black, white]) => write!(f, " # stderr black={} white={}", black.display(), white.display()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod game_data_testing {
+    use super::*;
+
+    #[test]
+    fn writes_wolve_format() {
+        let record = GameRecord {
+            moves: vec![
+                (Tile::Black, Action::Play(Coord::new(0, 0))),
+                (Tile::White, Action::Swap),
+                (Tile::Black, Action::Play(Coord::new(5, 9))),
+                (Tile::White, Action::Resign),
+            ],
+            winner: Tile::Black,
+            logs: None,
+        };
+
+        assert_eq!(record.to_string(), "b a1,w swap,b f10,w resign,B");
+        assert_eq!("b a1,w swap,b f10,w resign,B".parse(), Ok(record.clone()));
+
+        let logged = GameRecord {
+            logs: Some([PathBuf::from("logs/7-black.log"), PathBuf::from("my logs/7-white.log")]),
+            ..record
+        };
+        let line = "b a1,w swap,b f10,w resign,B # stderr black=logs/7-black.log white=my logs/7-white.log";
+        assert_eq!(logged.to_string(), line);
+        assert_eq!(line.parse(), Ok(logged));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let parse = |line: &str| line.parse::<GameRecord>().unwrap_err();
+
+        assert_eq!(parse("<<<<<<< HEAD"), ParseError::ConflictMarker);
+        assert_eq!(parse(">>>>>>> dd5dc4677863c72686be2b600e7f7cc34850a1c3"), ParseError::ConflictMarker);
+        assert_eq!(parse(""), ParseError::MissingWinner);
+        assert_eq!(parse("b a1,w a2,"), ParseError::MissingWinner);
+        assert_eq!(parse("b a1,w a2,X"), ParseError::BadWinner("X".to_string()));
+        assert_eq!(parse("b a1,wa2,B"), ParseError::BadField("wa2".to_string()));
+        assert_eq!(parse("b a1,x a2,B"), ParseError::BadColor("x".to_string()));
+        assert_eq!(parse("b a1,w 2a,B"), ParseError::BadMove("2a".to_string(), MoveError::BadLetter('2')));
+        assert_eq!(parse("b a1,B # good game"), ParseError::BadComment("good game".to_string()));
+    }
+
+    #[test]
+    fn validates_games() {
+        let validate = |line: &str| line.parse::<GameRecord>().unwrap().validate(3);
+
+        // Black connects top to bottom down the first column
+        assert_eq!(validate("b a1,w a2,b b1,w b2,b c1,B"), vec![]);
+        assert_eq!(validate("b a1,w a2,b b1,w resign,B"), vec![]);
+        assert_eq!(validate("b a1,w a2,b b1,w b2,b c1,W"),
+            vec![Problem::WinnerMismatch { declared: Tile::White, actual: Tile::Black }]);
+        assert_eq!(validate("b a1,w a2,b b1,w b2,W"),
+            vec![Problem::WinnerMismatch { declared: Tile::White, actual: Tile::Empty }]);
+
+        assert_eq!(validate("b a1,w a1,b b1,w b2,b c1,B"),
+            vec![Problem::Occupied { turn: 1, coord: Coord::new(0, 0) }]);
+        assert_eq!(validate("b a1,w a2,b b1,w d2,b c1,B"),
+            vec![Problem::OffBoard { turn: 3, coord: Coord::new(3, 1) }]);
+        assert_eq!(validate("b a1,b a2,w b1,B"), vec![
+            Problem::WrongTurn { turn: 1, expected: Tile::White },
+            Problem::WrongTurn { turn: 2, expected: Tile::Black },
+            Problem::WinnerMismatch { declared: Tile::Black, actual: Tile::Empty },
+        ]);
+        assert_eq!(validate("b a1,w a2,b b1,w b2,b c1,w c2,B"),
+            vec![Problem::MoveAfterEnd { turn: 5 }]);
+        assert_eq!(validate("b a1,w a2,b b1,w resign,b c1,B"),
+            vec![Problem::MoveAfterEnd { turn: 4 }]);
+
+        // Resigning out of turn is fine
+        assert_eq!(validate("b a1,w a2,b b1,b resign,W"), vec![]);
+
+        // The loser may still resign after the connection, but only as the last move
+        assert_eq!(validate("b a1,w a2,b b1,w b2,b c1,w resign,B"), vec![]);
+        assert_eq!(validate("b a1,w a2,b b1,w b2,b c1,w resign,W"),
+            vec![Problem::WinnerMismatch { declared: Tile::White, actual: Tile::Black }]);
+        assert_eq!(validate("b a1,w a2,b b1,w b2,b c1,w resign,b c2,B"),
+            vec![Problem::MoveAfterEnd { turn: 5 }]);
+    }
+
+    #[test]
+    fn validates_swaps() {
+        let validate = |line: &str| line.parse::<GameRecord>().unwrap().validate(3);
+
+        // The black stone on a2 becomes a white stone on b1, so white connects along row b
+        assert_eq!(validate("b a2,w swap,b a1,w b2,b c1,w b3,W"), vec![]);
+        assert_eq!(validate("b a2,w swap,b b1,W"),
+            vec![Problem::Occupied { turn: 2, coord: Coord::new(1, 0) }, Problem::WinnerMismatch {
+                declared: Tile::White, actual: Tile::Empty }]);
+        assert_eq!(validate("b a1,w a2,b swap,w resign,B"), vec![
+            Problem::MisplacedSwap { turn: 2 },
+        ]);
+    }
+}
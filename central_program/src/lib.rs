@@ -0,0 +1,20 @@
+// Everything the hex bot referee is built from, for use by other programs such as Rust bots: the
+// board and its rules, the protocol spoken with bots, an SDK for writing bots, bot processes,
+// whole games, recorded game files, datasets, ratings and a solver for small boards
+pub mod board;
+pub mod bot;
+pub mod connections;
+pub mod dataset;
+pub mod game;
+pub mod game_data;
+pub mod mcts;
+pub mod protocol;
+pub mod ratings;
+pub mod sdk;
+pub mod solver;
+pub mod tournament;
+pub mod units;
+
+pub use board::{Board, Coord, Move, MoveError, Symmetry, Tile};
+pub use bot::Bot;
+pub use protocol::Message;
@@ -0,0 +1,240 @@
+// Monte Carlo tree search with UCT. Positions are valued by filling the rest of the board with
+// random moves, which in hex always leaves exactly one winner
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::board::{Board, Coord, Move, Tile};
+use crate::sdk::HexBot;
+
+// Most of `time_left` a single move may use, to leave room for overhead
+const TIME_LEFT_SHARE: f64 = 0.9;
+
+// Least time spent on a move out of `time_left`, however many moves seem to be left
+const MIN_MOVE_TIME: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Budget {
+    // Number of random games to play per move
+    Playouts(usize),
+    // Thinking time per move
+    Time(Duration),
+}
+
+#[derive(Debug)]
+struct Node {
+    // The move that led here, and who played it
+    mv: Option<Coord>,
+    player: Tile,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    // Moves that don't have a node yet. Empty once the game is over
+    untried: Vec<Coord>,
+    visits: u32,
+    // Playouts won by `player`
+    wins: u32,
+}
+
+impl Node {
+    fn new(mv: Option<Coord>, player: Tile, parent: Option<usize>, board: &Board) -> Self {
+        let untried = if board.has_win() == Tile::Empty { empty_tiles(board) } else { Vec::new() };
+        Self { mv, player, parent, children: Vec::new(), untried, visits: 0, wins: 0 }
+    }
+}
+
+#[derive(Debug)]
+pub struct Mcts {
+    rng: StdRng,
+    budget: Budget,
+    // Weight of the UCT exploration term. Higher values try less visited moves more often
+    pub exploration: f64,
+    time_left: Option<Duration>,
+}
+
+impl Mcts {
+    pub fn new(budget: Budget, seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            budget,
+            exploration: std::f64::consts::SQRT_2,
+            time_left: None,
+        }
+    }
+
+    // The most visited move for `color`. None if the board is full
+    pub fn search(&mut self, board: &Board, color: Tile) -> Option<Coord> {
+        let start = Instant::now();
+        let time_limit = [
+            match self.budget {
+                Budget::Time(time) => Some(time),
+                Budget::Playouts(_) => None,
+            },
+            self.time_left.map(|time| move_time(time, board)),
+        ].into_iter().flatten().min();
+
+        let mut nodes = vec![Node::new(None, color.opponent(), None, board)];
+        let mut playouts = 0;
+
+        loop {
+            let out_of_time = time_limit.is_some_and(|limit| start.elapsed() >= limit);
+            let out_of_playouts = matches!(self.budget, Budget::Playouts(n) if playouts >= n);
+
+            // Always play at least one playout, so that there's a move to pick
+            if playouts > 0 && (out_of_time || out_of_playouts) {
+                break;
+            }
+            self.iterate(&mut nodes, board);
+            playouts += 1;
+        }
+
+        nodes[0].children.iter()
+            .max_by_key(|&&child| nodes[child].visits)
+            .and_then(|&child| nodes[child].mv)
+    }
+
+    // Selection, expansion, a random playout, then backpropagation
+    fn iterate(&mut self, nodes: &mut Vec<Node>, root: &Board) {
+        let mut board = root.clone();
+        let mut node = 0;
+
+        while nodes[node].untried.is_empty() && !nodes[node].children.is_empty() {
+            node = self.select(nodes, node);
+            let mv = nodes[node].mv.unwrap();
+            board.set(mv.row, mv.col, nodes[node].player);
+        }
+
+        if !nodes[node].untried.is_empty() {
+            let untried = &mut nodes[node].untried;
+            let mv = untried.swap_remove(self.rng.gen_range(0..untried.len()));
+            let player = nodes[node].player.opponent();
+            board.set(mv.row, mv.col, player);
+
+            nodes.push(Node::new(Some(mv), player, Some(node), &board));
+            let child = nodes.len() - 1;
+            nodes[node].children.push(child);
+            node = child;
+        }
+
+        let winner = self.playout(&mut board, nodes[node].player.opponent());
+
+        let mut current = Some(node);
+        while let Some(i) = current {
+            nodes[i].visits += 1;
+            if nodes[i].player == winner {
+                nodes[i].wins += 1;
+            }
+            current = nodes[i].parent;
+        }
+    }
+
+    // The child with the highest upper confidence bound
+    fn select(&self, nodes: &[Node], parent: usize) -> usize {
+        let log_visits = (nodes[parent].visits as f64).ln();
+        let score = |child: usize| {
+            let node = &nodes[child];
+            let visits = node.visits as f64;
+            node.wins as f64 / visits + self.exploration * (log_visits / visits).sqrt()
+        };
+
+        *nodes[parent].children.iter()
+            .max_by(|&&a, &&b| score(a).partial_cmp(&score(b)).unwrap())
+            .unwrap()
+    }
+
+    // Fills the board with random moves, starting with `to_move`, and returns the winner
+    fn playout(&mut self, board: &mut Board, mut to_move: Tile) -> Tile {
+        if board.has_win() != Tile::Empty {
+            return board.has_win();
+        }
+
+        let mut empty = empty_tiles(board);
+        empty.shuffle(&mut self.rng);
+
+        for coord in empty {
+            board.set(coord.row, coord.col, to_move);
+            to_move = to_move.opponent();
+        }
+        board.has_win()
+    }
+}
+
+impl HexBot for Mcts {
+    fn choose_move(&mut self, board: &Board, color: Tile) -> Option<Move> {
+        let mv = self.search(board, color);
+        self.time_left = None;
+        mv.map(Move::Place)
+    }
+
+    fn time_left(&mut self, time: Duration) {
+        self.time_left = Some(time);
+    }
+}
+
+// How long to think out of `time_left`. That's the whole game clock under `--game-time`, so it's
+// spread over the moves this bot has left, about half of the empty tiles
+fn move_time(time_left: Duration, board: &Board) -> Duration {
+    let moves_left = (board.empty_count() / 2).max(1) as u32;
+    (time_left / moves_left).max(MIN_MOVE_TIME).min(time_left.mul_f64(TIME_LEFT_SHARE))
+}
+
+fn empty_tiles(board: &Board) -> Vec<Coord> {
+    let size = board.size();
+
+    (0..size * size)
+        .map(|i| Coord::new(i / size, i % size))
+        .filter(|coord| board.get(coord.row, coord.col) == Some(Tile::Empty))
+        .collect()
+}
+
+#[cfg(test)]
+mod mcts_testing {
+    use super::*;
+
+    #[test]
+    fn finds_winning_moves() {
+        // Black only needs c1 to join the top and bottom
+        let board = Board::from("B..|B.W|.W.|");
+        let mut mcts = Mcts::new(Budget::Playouts(2000), 1);
+        assert_eq!(mcts.search(&board, Tile::Black), Some(Coord::new(2, 0)));
+
+        // White has to block it
+        assert_eq!(mcts.search(&board, Tile::White), Some(Coord::new(2, 0)));
+    }
+
+    #[test]
+    fn stays_within_budget() {
+        let board = Board::new(11);
+
+        let mut mcts = Mcts::new(Budget::Time(Duration::from_millis(50)), 1);
+        let start = Instant::now();
+        assert!(mcts.search(&board, Tile::Black).is_some());
+        assert!(start.elapsed() < Duration::from_millis(500));
+
+        // The clock wins when it's shorter than the budget
+        let mut mcts = Mcts::new(Budget::Playouts(usize::MAX), 1);
+        mcts.time_left(Duration::from_millis(50));
+        let start = Instant::now();
+        assert!(matches!(mcts.choose_move(&board, Tile::White), Some(Move::Place(_))));
+        assert!(start.elapsed() < Duration::from_millis(500));
+
+        // A game clock is spread over the rest of the game
+        assert_eq!(move_time(Duration::from_secs(2), &Board::new(9)), Duration::from_secs(2) / 40);
+        assert_eq!(move_time(Duration::from_secs(2), &Board::from("B.|..|")), Duration::from_millis(1800));
+        assert_eq!(move_time(Duration::from_millis(5), &Board::new(9)), Duration::from_micros(4500));
+        assert_eq!(move_time(Duration::from_millis(100), &Board::new(9)), MIN_MOVE_TIME);
+
+        let full = Board::from("BW|WB|");
+        assert_eq!(Mcts::new(Budget::Playouts(10), 1).search(&full, Tile::Black), None);
+        assert_eq!(Mcts::new(Budget::Playouts(10), 1).choose_move(&full, Tile::Black), None);
+    }
+
+    #[test]
+    fn same_seed_same_moves() {
+        let board = Board::from("B....|.....|..W..|.....|.....|");
+        let first = Mcts::new(Budget::Playouts(500), 7).search(&board, Tile::Black);
+        let second = Mcts::new(Budget::Playouts(500), 7).search(&board, Tile::Black);
+        assert_eq!(first, second);
+    }
+}
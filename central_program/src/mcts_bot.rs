@@ -0,0 +1,45 @@
+// Reference bot that searches with Monte Carlo tree search. Much stronger than random_bot, and a
+// decent benchmark for a first real bot
+use clap::Parser;
+use std::time::Duration;
+use sentience_validator::units::parse_duration;
+use sentience_validator::mcts::{Budget, Mcts};
+use sentience_validator::sdk;
+use sentience_validator::Tile;
+
+#[derive(Parser, Debug)]
+#[clap(name = "mcts_bot")]
+#[clap(about = "Hex bot that plays with Monte Carlo tree search", long_about = None)]
+struct Cli {
+    /// This bot's color. White is left->right
+    #[clap(value_parser = ["black", "white"])]
+    color: String,
+    /// Random games to play out per move
+    #[clap(long, default_value = "10000", conflicts_with = "time")]
+    playouts: usize,
+    /// Think for this long per move instead of a fixed number of playouts. Ex: 500ms, 2s
+    #[clap(long, value_parser = parse_duration)]
+    time: Option<Duration>,
+    /// Weight of the exploration term. Higher values try less promising moves more often
+    #[clap(long, default_value = "1.4142135")]
+    exploration: f64,
+    /// Seed for the random playouts. Picked at random and printed to stderr if not given
+    #[clap(long)]
+    seed: Option<u64>,
+}
+
+fn main() -> std::io::Result<()> {
+    let cli = Cli::parse();
+    let seed = cli.seed.unwrap_or_else(rand::random);
+    eprintln!("mcts_bot seed {}", seed);
+
+    let budget = match cli.time {
+        Some(time) => Budget::Time(time),
+        None => Budget::Playouts(cli.playouts),
+    };
+    let mut bot = Mcts::new(budget, seed);
+    bot.exploration = cli.exploration;
+
+    let color = if cli.color == "black" { Tile::Black } else { Tile::White };
+    sdk::run_with(bot, color, std::io::stdin().lock(), std::io::stdout().lock())
+}
@@ -0,0 +1,190 @@
+// Messages sent by the central program to bots, one per line. Parsing is strict: a message is a
+// command and its arguments separated by single spaces, with nothing else on the line
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::board::{Coord, MoveError, MAX_SIZE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    // Start over on an empty board with this side length
+    InitBoard(u8),
+    ShowBoard,
+    MakeMove,
+    // The opponent placed a stone
+    SetO(Coord),
+    // Place one of the bot's own stones
+    SetY(Coord),
+    // The opponent swapped
+    Swap,
+    Unset(Coord),
+    CheckWin,
+    // Most time the bot may take for its next move, sent as whole milliseconds: what's left on its
+    // game clock, or the move time if that's shorter. Only sent to bots that opted in with
+    // `--send-time-left`
+    TimeLeft(Duration),
+    Quit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError {
+    Empty,
+    UnknownCommand(String),
+    WrongArgumentCount { command: &'static str, expected: usize, found: usize },
+    BadSize(String),
+    BadCoord(MoveError),
+    BadTime(String),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty message"),
+            Self::UnknownCommand(s) => write!(f, "unknown command `{}`", s.escape_debug()),
+            Self::WrongArgumentCount { command, expected, found } =>
+                write!(f, "`{}` takes {} argument(s), not {}", command, expected, found),
+            Self::BadSize(s) => write!(f, "board size `{}` is not between 1 and {}", s.escape_debug(), MAX_SIZE),
+            Self::BadCoord(e) => write!(f, "bad coordinate: {}", e),
+            Self::BadTime(s) => write!(f, "time `{}` is not a whole number of milliseconds", s.escape_debug()),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+// Every command and how many arguments it takes
+const COMMANDS: [(&str, usize); 10] = [
+    ("init_board", 1),
+    ("show_board", 0),
+    ("make_move", 0),
+    ("seto", 1),
+    ("sety", 1),
+    ("swap", 0),
+    ("unset", 1),
+    ("check_win", 0),
+    ("time_left", 1),
+    ("quit", 0),
+];
+
+impl Message {
+    pub fn command(&self) -> &'static str {
+        match self {
+            Self::InitBoard(_) => "init_board",
+            Self::ShowBoard => "show_board",
+            Self::MakeMove => "make_move",
+            Self::SetO(_) => "seto",
+            Self::SetY(_) => "sety",
+            Self::Swap => "swap",
+            Self::Unset(_) => "unset",
+            Self::CheckWin => "check_win",
+            Self::TimeLeft(_) => "time_left",
+            Self::Quit => "quit",
+        }
+    }
+}
+
+impl FromStr for Message {
+    type Err = ProtocolError;
+
+    // A single trailing line ending is allowed, as it's what a line read from a pipe ends with
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.strip_suffix('\n').unwrap_or(line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if line.is_empty() {
+            return Err(ProtocolError::Empty);
+        }
+
+        let mut words = line.split(' ');
+        let command = words.next().unwrap();
+        let args: Vec<&str> = words.collect();
+        let coord = |arg: &str| Coord::parse(arg, MAX_SIZE).map_err(ProtocolError::BadCoord);
+
+        Ok(match (command, args.as_slice()) {
+            ("init_board", [size]) => match size.parse::<u8>() {
+                // Leading zeros or signs would round trip differently
+                Ok(n) if (1..=MAX_SIZE as u8).contains(&n) && n.to_string() == *size => Self::InitBoard(n),
+                _ => return Err(ProtocolError::BadSize(size.to_string())),
+            },
+            ("show_board", []) => Self::ShowBoard,
+            ("make_move", []) => Self::MakeMove,
+            ("seto", [arg]) => Self::SetO(coord(arg)?),
+            ("sety", [arg]) => Self::SetY(coord(arg)?),
+            ("swap", []) => Self::Swap,
+            ("unset", [arg]) => Self::Unset(coord(arg)?),
+            ("check_win", []) => Self::CheckWin,
+            ("time_left", [ms]) => match ms.parse::<u64>() {
+                Ok(n) if n.to_string() == *ms => Self::TimeLeft(Duration::from_millis(n)),
+                _ => return Err(ProtocolError::BadTime(ms.to_string())),
+            },
+            ("quit", []) => Self::Quit,
+            _ => return Err(match COMMANDS.iter().find(|(name, _)| *name == command) {
+                Some(&(command, expected)) => {
+                    ProtocolError::WrongArgumentCount { command, expected, found: args.len() }
+                }
+                None => ProtocolError::UnknownCommand(command.to_string()),
+            }),
+        })
+    }
+}
+
+// Writes the message without a line ending. Ex: "seto c4"
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InitBoard(size) => write!(f, "{} {}", self.command(), size),
+            Self::SetO(coord) | Self::SetY(coord) | Self::Unset(coord) => write!(f, "{} {}", self.command(), coord),
+            Self::TimeLeft(time) => write!(f, "{} {}", self.command(), time.as_millis()),
+            _ => write!(f, "{}", self.command()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod protocol_testing {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let messages = [
+            Message::InitBoard(11),
+            Message::ShowBoard,
+            Message::MakeMove,
+            Message::SetO(Coord::new(2, 3)),
+            Message::SetY(Coord::new(0, 10)),
+            Message::Swap,
+            Message::Unset(Coord::new(25, 25)),
+            Message::CheckWin,
+            Message::TimeLeft(Duration::from_millis(1500)),
+            Message::Quit,
+        ];
+
+        for message in messages {
+            assert_eq!(message.to_string().parse(), Ok(message));
+            assert_eq!(format!("{}\n", message).parse(), Ok(message));
+        }
+
+        assert_eq!(Message::SetO(Coord::new(2, 3)).to_string(), "seto c4");
+        assert_eq!(Message::InitBoard(11).to_string(), "init_board 11");
+        assert_eq!("make_move\r\n".parse(), Ok(Message::MakeMove));
+    }
+
+    #[test]
+    fn rejects_sloppy_messages() {
+        let parse = |line: &str| line.parse::<Message>().unwrap_err();
+
+        assert_eq!(parse(""), ProtocolError::Empty);
+        assert_eq!(parse("\n"), ProtocolError::Empty);
+        assert_eq!(parse("setx a1"), ProtocolError::UnknownCommand("setx".to_string()));
+        assert_eq!(parse(" seto a1"), ProtocolError::UnknownCommand("".to_string()));
+        assert_eq!(parse("seto"), ProtocolError::WrongArgumentCount { command: "seto", expected: 1, found: 0 });
+        assert_eq!(parse("seto  a1"), ProtocolError::WrongArgumentCount { command: "seto", expected: 1, found: 2 });
+        assert_eq!(parse("quit now"), ProtocolError::WrongArgumentCount { command: "quit", expected: 0, found: 1 });
+        assert_eq!(parse("init_board 011"), ProtocolError::BadSize("011".to_string()));
+        assert_eq!(parse("init_board 0"), ProtocolError::BadSize("0".to_string()));
+        assert_eq!(parse("init_board 27"), ProtocolError::BadSize("27".to_string()));
+        assert_eq!(parse("sety A1"), ProtocolError::BadCoord(MoveError::BadLetter('A')));
+        assert_eq!(parse("time_left 1.5s"), ProtocolError::BadTime("1.5s".to_string()));
+        assert_eq!(parse("make_move\n\n"), ProtocolError::UnknownCommand("make_move\n".to_string()));
+    }
+}
@@ -0,0 +1,68 @@
+// Plays uniformly random moves, like random_bot/bot.py, without needing python installed. Handy as
+// a baseline opponent and for checking the central program itself
+use clap::Parser;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sentience_validator::sdk::{self, HexBot};
+use sentience_validator::{Board, Coord, Move, Tile};
+
+#[derive(Parser, Debug)]
+#[clap(name = "random_bot")]
+#[clap(about = "Hex bot that plays random legal moves", long_about = None)]
+struct Cli {
+    /// This bot's color. White is left->right
+    #[clap(value_parser = ["black", "white"])]
+    color: String,
+    /// Seed for the random moves. Games with the same seeds and opponents play out the same.
+    /// Picked at random and printed to stderr if not given
+    #[clap(long)]
+    seed: Option<u64>,
+    /// Chance of swapping when given the opportunity on move 2
+    #[clap(long, default_value = "1")]
+    swap_chance: f64,
+}
+
+struct RandomBot {
+    rng: StdRng,
+    swap_chance: f64,
+    // Whether either side has swapped this game
+    swapped: bool,
+}
+
+impl HexBot for RandomBot {
+    fn choose_move(&mut self, board: &Board, color: Tile) -> Option<Move> {
+        let size = board.size();
+        let empty: Vec<Coord> = (0..size * size)
+            .map(|i| Coord::new(i / size, i % size))
+            .filter(|coord| board.get(coord.row, coord.col) == Some(Tile::Empty))
+            .collect();
+
+        // Only white's first move sees exactly one stone, unless that move was a swap. Black sees
+        // one too after a stone swap, which doesn't change anyone's color
+        let can_swap = color == Tile::White && empty.len() + 1 == size * size && !self.swapped;
+        if can_swap && self.rng.gen_bool(self.swap_chance.clamp(0.0, 1.0)) {
+            return Some(Move::Swap);
+        }
+        // The sdk resigns for us instead of asking when there's no empty tile left
+        Some(Move::Place(empty[self.rng.gen_range(0..empty.len())]))
+    }
+
+    fn new_game(&mut self, _size: u8) {
+        self.swapped = false;
+    }
+
+    fn swapped(&mut self) {
+        self.swapped = true;
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    let cli = Cli::parse();
+    let seed = cli.seed.unwrap_or_else(rand::random);
+    eprintln!("random_bot seed {}", seed);
+
+    let color = if cli.color == "black" { Tile::Black } else { Tile::White };
+    let bot = RandomBot { rng: StdRng::seed_from_u64(seed), swap_chance: cli.swap_chance, swapped: false };
+
+    sdk::run_with(bot, color, std::io::stdin().lock(), std::io::stdout().lock())
+}
@@ -0,0 +1,319 @@
+// Ratings are on the Elo scale: a bot rated 400 points above another is expected to win 10 of
+// every 11 games against it. Games are given as (winner, loser) pairs of bot indices
+use std::f64::consts::LN_10;
+
+const ELO_PER_NAT: f64 = 400.0 / LN_10;
+
+// Two sided 95% confidence
+const Z_95: f64 = 1.959964;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rating {
+    pub elo: f64,
+    // Half the width of the 95% confidence interval
+    pub interval: f64,
+}
+
+// Number of times each row bot beat each column bot
+pub fn head_to_head(bots: usize, games: &[(usize, usize)]) -> Vec<Vec<usize>> {
+    let mut wins = vec![vec![0; bots]; bots];
+
+    for &(winner, loser) in games {
+        wins[winner][loser] += 1;
+    }
+    wins
+}
+
+// Classic incremental Elo, starting everyone at 0. Depends on the order the games were played in
+pub fn elo(bots: usize, games: &[(usize, usize)], k: f64) -> Vec<f64> {
+    let mut ratings = vec![0.0; bots];
+
+    for &(winner, loser) in games {
+        let expected = 1.0 / (1.0 + 10f64.powf((ratings[loser] - ratings[winner]) / 400.0));
+        ratings[winner] += k * (1.0 - expected);
+        ratings[loser] -= k * (1.0 - expected);
+    }
+    ratings
+}
+
+// Maximum likelihood Bradley-Terry ratings, centered on an average of 0. Every bot also gets one
+// win and one loss against an imaginary average bot, so that undefeated or winless bots still
+// have finite ratings
+pub fn bradley_terry(bots: usize, games: &[(usize, usize)]) -> Vec<Rating> {
+    let wins = head_to_head(bots, games);
+    let played = |i: usize, j: usize| (wins[i][j] + wins[j][i]) as f64;
+    let total_wins: Vec<f64> = wins.iter().map(|row| row.iter().sum::<usize>() as f64 + 1.0).collect();
+
+    // Minorization-maximization updates (Hunter, 2004). Strengths are exp(rating in nats)
+    let mut strengths = vec![1.0; bots];
+    for _ in 0..10_000 {
+        let mut next = vec![0.0; bots];
+
+        for i in 0..bots {
+            let mut denominator = 2.0 / (strengths[i] + 1.0);
+            for j in (0..bots).filter(|&j| j != i) {
+                denominator += played(i, j) / (strengths[i] + strengths[j]);
+            }
+            next[i] = total_wins[i] / denominator;
+        }
+
+        let change = next.iter().zip(&strengths).map(|(a, b)| (a / b).ln().abs()).fold(0.0, f64::max);
+        strengths = next;
+        if change < 1e-10 {
+            break;
+        }
+    }
+
+    // The covariance of the log strengths is the inverse of the log likelihood's negated Hessian
+    let mut hessian = vec![vec![0.0; bots]; bots];
+    for i in 0..bots {
+        let p = strengths[i] / (strengths[i] + 1.0);
+        hessian[i][i] += 2.0 * p * (1.0 - p);
+
+        for j in (0..bots).filter(|&j| j != i) {
+            let p = strengths[i] / (strengths[i] + strengths[j]);
+            let information = played(i, j) * p * (1.0 - p);
+            hessian[i][i] += information;
+            hessian[i][j] -= information;
+        }
+    }
+    let covariance = invert(hessian);
+
+    let n = bots.max(1) as f64;
+    let logs: Vec<f64> = strengths.iter().map(|s| s.ln()).collect();
+    let mean = logs.iter().sum::<f64>() / n;
+
+    // Ratings are relative to the average, so their variance is that of (log - mean)
+    let row_sums: Vec<f64> = covariance.iter().map(|row| row.iter().sum()).collect();
+    let total = row_sums.iter().sum::<f64>();
+
+    logs.iter()
+        .zip(covariance.iter().enumerate())
+        .map(|(log, (i, row))| {
+            let variance = row[i] - 2.0 * row_sums[i] / n + total / (n * n);
+
+            Rating {
+                elo: (log - mean) * ELO_PER_NAT,
+                interval: Z_95 * variance.max(0.0).sqrt() * ELO_PER_NAT,
+            }
+        })
+        .collect()
+}
+
+// Gauss-Jordan elimination with partial pivoting. The matrix must be invertible
+fn invert(mut matrix: Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+    let n = matrix.len();
+    let mut inverse: Vec<Vec<f64>> = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&a, &b| matrix[a][col].abs().total_cmp(&matrix[b][col].abs()))
+            .unwrap();
+        matrix.swap(col, pivot);
+        inverse.swap(col, pivot);
+
+        let scale = matrix[col][col];
+        for j in 0..n {
+            matrix[col][j] /= scale;
+            inverse[col][j] /= scale;
+        }
+
+        for row in (0..n).filter(|&row| row != col) {
+            let factor = matrix[row][col];
+            for j in 0..n {
+                matrix[row][j] -= factor * matrix[col][j];
+                inverse[row][j] -= factor * inverse[col][j];
+            }
+        }
+    }
+    inverse
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SprtDecision {
+    // The new bot is no better than elo0
+    AcceptH0,
+    // The new bot is at least elo1 stronger
+    AcceptH1,
+    Continue,
+}
+
+// Sequential probability ratio test for A/B matches. Hex has no draws, so each game is a plain
+// win or loss for the new bot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    elo0: f64,
+    elo1: f64,
+    alpha: f64,
+    beta: f64,
+}
+
+impl Sprt {
+    // Tests whether the new bot is `elo0` or `elo1` points stronger, with false positive and false
+    // negative rates of `alpha` and `beta`
+    pub fn new(elo0: f64, elo1: f64, alpha: f64, beta: f64) -> Result<Self, String> {
+        if !elo0.is_finite() || !elo1.is_finite() || elo0 >= elo1 {
+            return Err(format!("elo0 ({}) must be lower than elo1 ({})", elo0, elo1));
+        }
+        for (name, rate) in [("alpha", alpha), ("beta", beta)] {
+            if !(rate > 0.0 && rate < 1.0) {
+                return Err(format!("{} ({}) must be between 0 and 1", name, rate));
+            }
+        }
+        Ok(Sprt { elo0, elo1, alpha, beta })
+    }
+
+    // Log likelihood ratio of H1 over H0 after the new bot won and lost the given games
+    pub fn llr(&self, wins: usize, losses: usize) -> f64 {
+        let p0 = expected_score(self.elo0);
+        let p1 = expected_score(self.elo1);
+
+        wins as f64 * (p1 / p0).ln() + losses as f64 * ((1.0 - p1) / (1.0 - p0)).ln()
+    }
+
+    // The test stops once the log likelihood ratio leaves this range
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    pub fn decide(&self, wins: usize, losses: usize) -> SprtDecision {
+        let llr = self.llr(wins, losses);
+        let (lower, upper) = self.bounds();
+
+        if llr <= lower {
+            SprtDecision::AcceptH0
+        } else if llr >= upper {
+            SprtDecision::AcceptH1
+        } else {
+            SprtDecision::Continue
+        }
+    }
+}
+
+// Chance of winning against an opponent rated `elo` points lower
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+// Prints the head to head matrix followed by both kinds of ratings, best bot first. Each cell of
+// the matrix holds the row bot's wins and losses against the column bot. Ex: "3-2"
+pub fn print_report(names: &[String], games: &[(usize, usize)]) {
+    let n = names.len();
+    let wins = head_to_head(n, games);
+    let width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+
+    print!("{:width$}", "", width = width);
+    for bot in 0..n {
+        print!(" {:>7}", bot + 1);
+    }
+    println!(" {:>7}", "Wins");
+
+    for (row, name) in names.iter().enumerate() {
+        print!("{:width$}", name, width = width);
+        for (col, against) in wins.iter().enumerate() {
+            if row == col {
+                print!(" {:>7}", "-");
+            } else {
+                print!(" {:>7}", format!("{}-{}", wins[row][col], against[row]));
+            }
+        }
+        println!(" {:>7}", wins[row].iter().sum::<usize>());
+    }
+
+    let bradley_terry = bradley_terry(n, games);
+    let elo = elo(n, games, 16.0);
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&a, &b| bradley_terry[b].elo.total_cmp(&bradley_terry[a].elo));
+
+    println!();
+    println!("{:width$} {:>8} {:>8} {:>8}", "", "BT", "95% CI", "Elo", width = width);
+    for bot in order {
+        // Adding 0 turns -0.0 into 0.0
+        println!("{:width$} {:>8.1} {:>8} {:>8.1}", names[bot], bradley_terry[bot].elo + 0.0,
+            format!("±{:.1}", bradley_terry[bot].interval), elo[bot] + 0.0, width = width);
+    }
+}
+
+#[cfg(test)]
+mod ratings_testing {
+    use super::*;
+
+    #[test]
+    fn head_to_head_counts() {
+        let wins = head_to_head(3, &[(0, 1), (0, 1), (1, 0), (2, 0)]);
+        assert_eq!(wins, vec![vec![0, 2, 0], vec![1, 0, 0], vec![1, 0, 0]]);
+    }
+
+    #[test]
+    fn elo_is_zero_sum() {
+        let ratings = elo(3, &[(0, 1), (0, 2), (1, 2), (0, 1)], 32.0);
+        assert!(ratings.iter().sum::<f64>().abs() < 1e-9);
+        assert!(ratings[0] > ratings[1] && ratings[1] > ratings[2]);
+
+        // Beating an equal opponent is worth half of k
+        assert_eq!(elo(2, &[(1, 0)], 32.0), vec![-16.0, 16.0]);
+    }
+
+    #[test]
+    fn bradley_terry_matches_win_rate() {
+        // 75 wins out of 100 is 400 * log10(3) ≈ 191 Elo, pulled slightly inwards by the prior
+        let mut games = vec![(0, 1); 75];
+        games.extend(vec![(1, 0); 25]);
+        let ratings = bradley_terry(2, &games);
+
+        let difference = ratings[0].elo - ratings[1].elo;
+        assert!(180.0 < difference && difference < 191.0, "{}", difference);
+        assert!((ratings[0].elo + ratings[1].elo).abs() < 1e-6);
+        assert!(ratings[0].interval > 0.0);
+
+        // More games give tighter intervals
+        let many = bradley_terry(2, &games.repeat(10));
+        assert!(many[0].interval < ratings[0].interval / 2.0);
+    }
+
+    #[test]
+    fn bradley_terry_undefeated_is_finite() {
+        let ratings = bradley_terry(3, &[(0, 1), (0, 2), (0, 1), (1, 2)]);
+        assert!(ratings.iter().all(|r| r.elo.is_finite() && r.interval.is_finite()));
+        assert!(ratings[0].elo > ratings[1].elo && ratings[1].elo > ratings[2].elo);
+    }
+
+    #[test]
+    fn invert_matrix() {
+        let inverse = invert(vec![vec![4.0, 7.0], vec![2.0, 6.0]]);
+        let expected = [[0.6, -0.7], [-0.2, 0.4]];
+
+        for i in 0..2 {
+            for j in 0..2 {
+                assert!((inverse[i][j] - expected[i][j]).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn sprt_decisions() {
+        let sprt = Sprt::new(0.0, 50.0, 0.05, 0.05).unwrap();
+
+        assert_eq!(sprt.llr(0, 0), 0.0);
+        assert_eq!(sprt.decide(0, 0), SprtDecision::Continue);
+        assert_eq!(sprt.decide(60, 20), SprtDecision::AcceptH1);
+        assert_eq!(sprt.decide(20, 60), SprtDecision::AcceptH0);
+        assert_eq!(sprt.decide(10, 10), SprtDecision::Continue);
+
+        let (lower, upper) = sprt.bounds();
+        assert!((upper - 19f64.ln()).abs() < 1e-12 && (lower + 19f64.ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn sprt_rejects_bad_parameters() {
+        assert!(Sprt::new(30.0, 0.0, 0.05, 0.05).is_err());
+        assert!(Sprt::new(10.0, 10.0, 0.05, 0.05).is_err());
+        assert!(Sprt::new(f64::NAN, 10.0, 0.05, 0.05).is_err());
+        assert!(Sprt::new(0.0, 10.0, 0.0, 0.05).is_err());
+        assert!(Sprt::new(0.0, 10.0, 0.05, 1.0).is_err());
+        assert!(Sprt::new(0.0, 10.0, 0.05, -0.1).is_err());
+        assert!(Sprt::new(0.0, 10.0, 0.5, 0.5).is_ok());
+    }
+}
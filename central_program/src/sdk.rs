@@ -0,0 +1,242 @@
+// Everything a bot needs besides choosing moves. Implement `HexBot` and hand it to `run`, which
+// keeps track of the board and answers the central program the same way `random_bot` does:
+//
+//     struct FirstEmpty;
+//
+//     impl HexBot for FirstEmpty {
+//         fn choose_move(&mut self, board: &Board, _color: Tile) -> Option<Move> {
+//             ...
+//         }
+//     }
+//
+//     fn main() -> std::io::Result<()> {
+//         sentience_validator::sdk::run(FirstEmpty)
+//     }
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+use crate::board::{Board, Move, Tile};
+use crate::protocol::Message;
+
+// Board size until the central program sends `init_board`
+const DEFAULT_SIZE: u8 = 11;
+
+pub trait HexBot {
+    // Picks a move for `color` on the current board, or None to resign. Only called when it's the
+    // bot's turn and there's an empty tile to play
+    fn choose_move(&mut self, board: &Board, color: Tile) -> Option<Move>;
+
+    // Called before `choose_move` with the most time that move may take, if the central program
+    // was started with `--send-time-left`. Under a game clock that's all the time left for the game
+    fn time_left(&mut self, _time: Duration) {}
+
+    // Called when the board is reset to an empty one of the given size
+    fn new_game(&mut self, _size: u8) {}
+
+    // Called whenever either side swaps. A position right after a swap can look just like the
+    // position a swap was played in, so bots that swap need this to only do it once
+    fn swapped(&mut self) {}
+}
+
+impl<B: HexBot + ?Sized> HexBot for &mut B {
+    fn choose_move(&mut self, board: &Board, color: Tile) -> Option<Move> {
+        (**self).choose_move(board, color)
+    }
+
+    fn time_left(&mut self, time: Duration) {
+        (**self).time_left(time)
+    }
+
+    fn new_game(&mut self, size: u8) {
+        (**self).new_game(size)
+    }
+
+    fn swapped(&mut self) {
+        (**self).swapped()
+    }
+}
+
+// Plays as the color given as the first command line argument, "black" or "white", over stdin
+// and stdout until told to quit
+pub fn run(bot: impl HexBot) -> io::Result<()> {
+    let color = match std::env::args().nth(1).as_deref() {
+        Some("black") => Tile::Black,
+        Some("white") => Tile::White,
+        _ => {
+            let message = "The first argument must be black or white";
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        }
+    };
+
+    run_with(bot, color, io::stdin().lock(), io::stdout().lock())
+}
+
+// Same as `run`, with the color and streams given explicitly. Messages that can't be parsed are
+// reported on stderr and otherwise ignored, so that stdout only ever holds replies
+pub fn run_with(mut bot: impl HexBot, mut color: Tile, input: impl BufRead, mut output: impl Write)
+    -> io::Result<()>
+{
+    let mut board = Board::new(DEFAULT_SIZE);
+
+    for line in input.lines() {
+        let line = line?;
+        let message = match line.trim().parse::<Message>() {
+            Ok(message) => message,
+            Err(e) => {
+                eprintln!("Ignoring `{}`: {}", line.escape_debug(), e);
+                continue;
+            }
+        };
+
+        match message {
+            Message::InitBoard(size) => {
+                board = Board::new(size);
+                bot.new_game(size);
+            }
+            Message::ShowBoard => writeln!(output, "{}", board.to_compressed())?,
+            Message::SetO(coord) | Message::SetY(coord) | Message::Unset(coord)
+                if board.get(coord.row, coord.col).is_none() =>
+            {
+                eprintln!("Ignoring `{}`: {} is off the board", line.escape_debug(), coord);
+            }
+            // A full board means the bot lost track of the game. Replying `resign` forfeits it
+            // instead of leaving bots to panic looking for a free tile
+            Message::MakeMove if board.empty_count() == 0 => {
+                eprintln!("No empty tiles left to play, resigning");
+                writeln!(output, "resign")?;
+            }
+            Message::MakeMove => match bot.choose_move(&board, color) {
+                Some(mv) => {
+                    match mv {
+                        Move::Swap => {
+                            color = color.opponent();
+                            bot.swapped();
+                        }
+                        Move::Place(coord) if board.get(coord.row, coord.col) == Some(Tile::Empty) => {
+                            board.set(coord.row, coord.col, color);
+                        }
+                        // An illegal move forfeits the game, so there's no board left to keep up to date
+                        Move::Place(_) => {}
+                    }
+                    writeln!(output, "{}", mv)?;
+                }
+                None => writeln!(output, "resign")?,
+            },
+            // Tiles that are already taken are left alone, like `random_bot` does
+            Message::SetO(coord) | Message::SetY(coord)
+                if board.get(coord.row, coord.col) != Some(Tile::Empty) => {}
+            Message::SetO(coord) => board.set(coord.row, coord.col, color.opponent()),
+            Message::SetY(coord) => board.set(coord.row, coord.col, color),
+            Message::Swap => {
+                color = color.opponent();
+                bot.swapped();
+            }
+            Message::Unset(coord) => board.set(coord.row, coord.col, Tile::Empty),
+            Message::CheckWin => {
+                let result = match board.has_win() {
+                    Tile::Empty => 0,
+                    winner if winner == color => 1,
+                    _ => -1,
+                };
+                writeln!(output, "{}", result)?;
+            }
+            Message::TimeLeft(time) => bot.time_left(time),
+            Message::Quit => break,
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod sdk_testing {
+    use super::*;
+    use crate::board::Coord;
+
+    // Plays the first empty tile, reading left to right then top to bottom
+    #[derive(Default)]
+    struct FirstEmpty {
+        time_left: Option<Duration>,
+        games: Vec<u8>,
+        swaps: usize,
+    }
+
+    impl HexBot for FirstEmpty {
+        fn choose_move(&mut self, board: &Board, _color: Tile) -> Option<Move> {
+            let size = board.size();
+            let index = (0..size * size).find(|i| board.get(i / size, i % size) == Some(Tile::Empty))?;
+
+            Some(Move::Place(Coord::new(index / size, index % size)))
+        }
+
+        fn time_left(&mut self, time: Duration) {
+            self.time_left = Some(time);
+        }
+
+        fn new_game(&mut self, size: u8) {
+            self.games.push(size);
+        }
+
+        fn swapped(&mut self) {
+            self.swaps += 1;
+        }
+    }
+
+    fn replies(color: Tile, input: &str) -> String {
+        let mut output = Vec::new();
+        run_with(FirstEmpty::default(), color, input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn plays_and_shows_board() {
+        assert_eq!(replies(Tile::Black, "init_board 3\nseto a1\nmake_move\nshow_board\n"), "a2\nWB.|...|...|\n");
+        assert_eq!(replies(Tile::White, "init_board 2\nsety a1\nseto b2\nunset a1\nshow_board\n"), "..|.B|\n");
+
+        // Taken tiles aren't overwritten
+        assert_eq!(replies(Tile::Black, "init_board 2\nsety a1\nseto a1\nshow_board\n"), "B.|..|\n");
+        assert_eq!(replies(Tile::Black, "init_board 2\nsety a1\ninit_board 2\nshow_board\n"), "..|..|\n");
+    }
+
+    #[test]
+    fn checks_for_wins() {
+        assert_eq!(replies(Tile::Black, "init_board 3\ncheck_win\n"), "0\n");
+        assert_eq!(replies(Tile::Black, "init_board 3\nsety a1\nsety b1\nsety c1\ncheck_win\n"), "1\n");
+        assert_eq!(replies(Tile::White, "init_board 3\nseto a1\nseto b1\nseto c1\ncheck_win\n"), "-1\n");
+        assert_eq!(replies(Tile::White, "init_board 3\nsety a1\nsety a2\nsety a3\ncheck_win\n"), "1\n");
+    }
+
+    #[test]
+    fn swaps_colors() {
+        // The same as `BotTest`'s swap tests: the board stays, the bot plays the other color
+        let expected = "..W.......|..........|..........|..........|..........|\
+            ..........|..........|..........|.B........|..........|\n";
+        assert_eq!(replies(Tile::Black, "init_board 10\nsety i2\nswap\nsety a3\nshow_board\n"), expected);
+        assert_eq!(replies(Tile::White, "init_board 10\nseto i2\nswap\nseto a3\nshow_board\n"), expected);
+    }
+
+    #[test]
+    fn skips_bad_messages_and_quits() {
+        assert_eq!(replies(Tile::Black, "init_board 2\nhello\nseto z9\n\nshow_board\nquit\nshow_board\n"), "..|..|\n");
+        assert_eq!(replies(Tile::Black, "init_board 2\r\nshow_board\r\n"), "..|..|\n");
+    }
+
+    #[test]
+    fn resigns_on_a_full_board() {
+        assert_eq!(replies(Tile::White, "init_board 1\nseto a1\nmake_move\nshow_board\n"), "resign\nB|\n");
+        assert_eq!(replies(Tile::Black, "init_board 2\nsety a1\nsety a2\nseto b1\nseto b2\nmake_move\n"), "resign\n");
+    }
+
+    #[test]
+    fn calls_hooks() {
+        let mut bot = FirstEmpty::default();
+        let mut output = Vec::new();
+        let input = "init_board 5\nsety a1\nswap\ninit_board 3\ntime_left 1500\nmake_move\n";
+
+        run_with(&mut bot, Tile::Black, input.as_bytes(), &mut output).unwrap();
+        assert_eq!(bot.time_left, Some(Duration::from_millis(1500)));
+        assert_eq!(bot.games, vec![5, 3]);
+        assert_eq!(bot.swaps, 1);
+        assert_eq!(output, b"a1\n");
+    }
+}
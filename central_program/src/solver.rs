@@ -0,0 +1,464 @@
+// Proves who wins a position with perfect play, using depth-first proof-number search (df-pn).
+// Every position is looked at from the side to move: its proof number is a guess at how many
+// positions still need solving to prove the side to move wins, its disproof number how many to
+// prove it loses. A position can never come back in hex, so transpositions are safe to share.
+//
+// Positions are copied off the `Board` into a pair of bitboards, which makes finding every tile
+// that wins on the spot a handful of shifts. That's what the search prunes with: a side facing two
+// such tiles has lost, and a side facing one has to block it
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+
+use crate::board::{Board, Coord, Tile};
+
+// Largest board whose tiles fit in the bitboards
+pub const MAX_SIZE: usize = 11;
+
+const INFINITY: u32 = u32::MAX;
+
+// Positions remembered before the unproven ones are forgotten, which keeps the table to around a
+// gigabyte. They'll be searched again if they come up, and if even the proven ones take up half
+// the table everything goes
+const TABLE_LIMIT: usize = 1 << 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Numbers {
+    proof: u32,
+    disproof: u32,
+}
+
+impl Numbers {
+    const WIN: Self = Self { proof: 0, disproof: INFINITY };
+    const LOSS: Self = Self { proof: INFINITY, disproof: 0 };
+}
+
+// One bit per tile, row by row. `a1` is the lowest bit
+type Bits = u128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Position {
+    black: Bits,
+    white: Bits,
+}
+
+impl Position {
+    fn stones(&self, color: Tile) -> Bits {
+        if color == Tile::Black { self.black } else { self.white }
+    }
+
+    fn play(mut self, tile: usize, color: Tile) -> Self {
+        match color {
+            Tile::Black => self.black |= 1 << tile,
+            _ => self.white |= 1 << tile,
+        }
+        self
+    }
+}
+
+// Masks for one board size
+#[derive(Debug, Clone, Copy)]
+struct Geometry {
+    size: usize,
+    all: Bits,
+    first_row: Bits,
+    last_row: Bits,
+    first_col: Bits,
+    last_col: Bits,
+}
+
+impl Geometry {
+    fn new(size: usize) -> Self {
+        let all = Bits::MAX >> (128 - size * size);
+        let row = (1 << size) - 1;
+        let col = (0..size).fold(0, |col, r| col | 1 << (r * size));
+
+        Self {
+            size,
+            all,
+            first_row: row,
+            last_row: row << (size * (size - 1)),
+            first_col: col,
+            last_col: col << (size - 1),
+        }
+    }
+
+    // Every tile next to one of the given tiles
+    fn neighbours(&self, bits: Bits) -> Bits {
+        let s = self.size;
+        let (not_first, not_last) = (self.all & !self.first_col, self.all & !self.last_col);
+
+        ((bits >> 1) & not_last)
+            | ((bits << 1) & not_first)
+            | (bits >> s)
+            | ((bits << s) & self.all)
+            | ((bits << (s - 1)) & not_last)
+            | ((bits >> (s - 1)) & not_first)
+    }
+
+    // The stones connected to any of the starting ones
+    fn flood(&self, start: Bits, stones: Bits) -> Bits {
+        let mut reached = start & stones;
+
+        loop {
+            let next = reached | (self.neighbours(reached) & stones);
+            if next == reached {
+                return reached;
+            }
+            reached = next;
+        }
+    }
+
+    fn edges(&self, color: Tile) -> (Bits, Bits) {
+        match color {
+            Tile::Black => (self.first_row, self.last_row),
+            _ => (self.first_col, self.last_col),
+        }
+    }
+
+    fn has_won(&self, position: &Position, color: Tile) -> bool {
+        let (start, end) = self.edges(color);
+        self.flood(start, position.stones(color)) & end != 0
+    }
+
+    // Empty tiles where the color would connect its edges
+    fn winning_tiles(&self, position: &Position, color: Tile) -> Bits {
+        let (start, end) = self.edges(color);
+        let stones = position.stones(color);
+        let empty = self.all & !position.black & !position.white;
+
+        let from_start = self.neighbours(self.flood(start, stones)) | start;
+        let from_end = self.neighbours(self.flood(end, stones)) | end;
+        empty & from_start & from_end
+    }
+}
+
+fn tiles(mut bits: Bits) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        let tile = bits.trailing_zeros() as usize;
+        bits &= bits.checked_sub(1)?;
+        Some(tile)
+    })
+}
+
+// Table lookups are most of the search, and the default hasher is built to resist attacks rather
+// than for speed. This is the multiply and rotate hash rustc uses
+#[derive(Debug, Default)]
+struct PositionHasher(u64);
+
+impl Hasher for PositionHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_u64(byte as u64);
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = (self.0.rotate_left(5) ^ n).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write_u64(n as u64);
+        self.write_u64((n >> 64) as u64);
+    }
+
+    fn write_u8(&mut self, n: u8) {
+        self.write_u64(n as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    pub winner: Tile,
+    // Every move that keeps the win for the side to move. Empty if it loses or the game is over
+    pub winning_moves: Vec<Coord>,
+}
+
+#[derive(Debug, Default)]
+pub struct Solver {
+    table: HashMap<(Position, bool), Numbers, BuildHasherDefault<PositionHasher>>,
+    // Positions searched so far, counting repeat visits
+    pub nodes: u64,
+    // Give up once this many positions have been searched
+    pub max_nodes: Option<u64>,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Who wins with `to_move` to play. None if it ran out of nodes first. Panics on boards over
+    // `MAX_SIZE`
+    pub fn winner(&mut self, board: &Board, to_move: Tile) -> Option<Tile> {
+        let (geometry, position) = bitboards(board);
+
+        match self.search(&geometry, position, to_move, INFINITY, INFINITY) {
+            Numbers { proof: 0, .. } => Some(to_move),
+            Numbers { disproof: 0, .. } => Some(to_move.opponent()),
+            _ => None,
+        }
+    }
+
+    // Who wins, and if it's `to_move` every move that wins. None if it ran out of nodes first
+    pub fn solve(&mut self, board: &Board, to_move: Tile) -> Option<Solution> {
+        let winner = self.winner(board, to_move)?;
+        let mut winning_moves = Vec::new();
+
+        if winner == to_move && board.has_win() == Tile::Empty {
+            for coord in empty_tiles(board) {
+                let mut child = board.clone();
+                child.set(coord.row, coord.col, to_move);
+
+                if self.winner(&child, to_move.opponent())? == to_move {
+                    winning_moves.push(coord);
+                }
+            }
+        }
+        Some(Solution { winner, winning_moves })
+    }
+
+    fn out_of_nodes(&self) -> bool {
+        self.max_nodes.is_some_and(|max| self.nodes >= max)
+    }
+
+    fn lookup(&self, position: Position, to_move: Tile) -> Option<Numbers> {
+        self.table.get(&(position, to_move == Tile::White)).copied()
+    }
+
+    fn store(&mut self, position: Position, to_move: Tile, numbers: Numbers) -> Numbers {
+        if self.table.len() >= TABLE_LIMIT {
+            self.table.retain(|_, numbers| numbers.proof == 0 || numbers.disproof == 0);
+
+            if self.table.len() >= TABLE_LIMIT / 2 {
+                self.table.clear();
+            }
+        }
+        self.table.insert((position, to_move == Tile::White), numbers);
+        numbers
+    }
+
+    // Searches until the proof number reaches `proof_limit` or the disproof number reaches
+    // `disproof_limit`, whichever comes first, and remembers the numbers it got to
+    fn search(&mut self, geometry: &Geometry, position: Position, to_move: Tile, proof_limit: u32,
+        disproof_limit: u32) -> Numbers
+    {
+        self.nodes += 1;
+        let other = to_move.opponent();
+
+        if geometry.has_won(&position, to_move) {
+            return self.store(position, to_move, Numbers::WIN);
+        }
+        if geometry.has_won(&position, other) {
+            return self.store(position, to_move, Numbers::LOSS);
+        }
+
+        // Connecting right away doesn't need a search, and neither does facing two tiles where
+        // the opponent connects. Facing one, it's the only move worth looking at
+        if geometry.winning_tiles(&position, to_move) != 0 {
+            return self.store(position, to_move, Numbers::WIN);
+        }
+        let threats = geometry.winning_tiles(&position, other);
+        let empty = geometry.all & !position.black & !position.white;
+        let mut moves = match threats.count_ones() {
+            0 => empty,
+            1 => threats,
+            _ => return self.store(position, to_move, Numbers::LOSS),
+        };
+
+        // Same goes for a tile where the opponent would make two winning tiles at once: the side
+        // to move has to take one of those three tiles, unless it makes a winning tile of its own
+        let mut must_play = empty;
+        for tile in tiles(if threats == 0 { empty } else { 0 }) {
+            let wins = geometry.winning_tiles(&position.play(tile, other), other);
+            if wins.count_ones() >= 2 {
+                must_play &= wins | 1 << tile;
+            }
+        }
+
+        // Children that haven't been searched start out as a guess: one good reply would be
+        // enough for the opponent, while refuting them means refuting all of their replies. A
+        // move that makes two winning tiles can't be answered, as the opponent has none of its own
+        let replies = empty.count_ones() - 1;
+        let mut children = Vec::with_capacity(moves.count_ones() as usize);
+
+        while moves != 0 {
+            let tile = moves.trailing_zeros() as usize;
+            moves &= moves - 1;
+
+            let child = position.play(tile, to_move);
+            let wins = geometry.winning_tiles(&child, to_move).count_ones();
+            if wins == 0 && must_play & 1 << tile == 0 {
+                continue;
+            }
+
+            let guess = if wins >= 2 { Numbers::LOSS } else { Numbers { proof: 1, disproof: replies.max(1) } };
+            children.push((child, self.lookup(child, other).unwrap_or(guess)));
+        }
+        if children.is_empty() {
+            return self.store(position, to_move, Numbers::LOSS);
+        }
+
+        loop {
+            // The side to move needs a single child where the opponent loses, and loses only
+            // once every child is won by the opponent
+            let mut numbers = Numbers { proof: INFINITY, disproof: 0 };
+            let (mut best, mut best_proof, mut second_disproof) = (0, 0, INFINITY);
+
+            for (i, &(_, child)) in children.iter().enumerate() {
+                if child.disproof < numbers.proof {
+                    second_disproof = numbers.proof;
+                    numbers.proof = child.disproof;
+                    best = i;
+                    best_proof = child.proof;
+                } else if child.disproof < second_disproof {
+                    second_disproof = child.disproof;
+                }
+                numbers.disproof = numbers.disproof.saturating_add(child.proof);
+            }
+
+            if numbers.proof >= proof_limit || numbers.disproof >= disproof_limit || self.out_of_nodes() {
+                return self.store(position, to_move, numbers);
+            }
+
+            // Look deeper into the child closest to a proof, until it stops being the closest.
+            // Going a quarter past the second best child saves switching back and forth (df-pn+)
+            let child_proof_limit = disproof_limit - (numbers.disproof - best_proof);
+            let child_disproof_limit = proof_limit.min(second_disproof.saturating_add(second_disproof / 4 + 1));
+
+            // Only that child's numbers are read back. Others can shift through transpositions, but
+            // staying a little out of date only costs time, never a wrong proof
+            children[best].1 = self.search(geometry, children[best].0, other, child_proof_limit, child_disproof_limit);
+        }
+    }
+}
+
+fn bitboards(board: &Board) -> (Geometry, Position) {
+    let size = board.size();
+    assert!(size <= MAX_SIZE, "Boards over {}x{} are too big to solve", MAX_SIZE, MAX_SIZE);
+
+    let mut position = Position { black: 0, white: 0 };
+    for i in 0..size * size {
+        match board.get(i / size, i % size) {
+            Some(Tile::Black) => position.black |= 1 << i,
+            Some(Tile::White) => position.white |= 1 << i,
+            _ => {}
+        }
+    }
+    (Geometry::new(size), position)
+}
+
+fn empty_tiles(board: &Board) -> Vec<Coord> {
+    let size = board.size();
+
+    (0..size * size)
+        .map(|i| Coord::new(i / size, i % size))
+        .filter(|coord| board.get(coord.row, coord.col) == Some(Tile::Empty))
+        .collect()
+}
+
+// The side to move in a game without a swap, where black moves first
+pub fn side_to_move(board: &Board) -> Tile {
+    let size = board.size();
+    let count = |color| (0..size * size).filter(|i| board.get(i / size, i % size) == Some(color)).count();
+
+    if count(Tile::Black) > count(Tile::White) { Tile::White } else { Tile::Black }
+}
+
+#[cfg(test)]
+mod solver_testing {
+    use super::*;
+
+    fn play(board: &Board, coord: Coord, color: Tile) -> Board {
+        let mut child = board.clone();
+        child.set(coord.row, coord.col, color);
+        child
+    }
+
+    // Plain minimax over every move, to check the solver against
+    fn brute_force(board: &Board, to_move: Tile) -> Tile {
+        if board.has_win() != Tile::Empty {
+            return board.has_win();
+        }
+        let wins = empty_tiles(board).into_iter()
+            .any(|coord| brute_force(&play(board, coord, to_move), to_move.opponent()) == to_move);
+
+        if wins { to_move } else { to_move.opponent() }
+    }
+
+    #[test]
+    fn bitboards_match_board() {
+        let board = Board::from("BW..|B.W.|.W..|B..W|");
+        let (geometry, position) = bitboards(&board);
+        let at = |coords: &[(usize, usize)]| coords.iter().fold(0, |bits, (r, c)| bits | 1 << (r * 4 + c));
+
+        assert_eq!(geometry.neighbours(at(&[(0, 0)])), at(&[(0, 1), (1, 0)]));
+        assert_eq!(geometry.neighbours(at(&[(1, 3)])), at(&[(0, 3), (1, 2), (2, 2), (2, 3)]));
+        assert_eq!(geometry.neighbours(at(&[(2, 1)])), at(&[(1, 1), (1, 2), (2, 0), (2, 2), (3, 0), (3, 1)]));
+        assert!(!geometry.has_won(&position, Tile::Black));
+        assert_eq!(geometry.winning_tiles(&position, Tile::Black), at(&[(2, 0)]));
+        assert_eq!(geometry.winning_tiles(&position, Tile::White), 0);
+
+        for size in 2..=MAX_SIZE {
+            let geometry = Geometry::new(size);
+            assert_eq!(geometry.all.count_ones() as usize, size * size);
+            assert_eq!(geometry.neighbours(geometry.all), geometry.all);
+        }
+    }
+
+    #[test]
+    fn solves_tiny_boards() {
+        let mut solver = Solver::new();
+        let moves = |solution: Solution| solution.winning_moves.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+
+        let solution = solver.solve(&Board::new(1), Tile::Black).unwrap();
+        assert_eq!(solution.winner, Tile::Black);
+        assert_eq!(moves(solution), ["a1"]);
+
+        // a1 and b2 each only reach one tile on the far edge, which white takes
+        let solution = solver.solve(&Board::new(2), Tile::Black).unwrap();
+        assert_eq!(moves(solution), ["a2", "b1"]);
+        let solution = solver.solve(&Board::new(2), Tile::White).unwrap();
+        assert_eq!(moves(solution), ["a2", "b1"]);
+
+        // Whoever moves first wins on an empty board, and on 4x4 only along the short diagonal
+        assert_eq!(solver.winner(&Board::new(3), Tile::White), Some(Tile::White));
+        assert_eq!(moves(solver.solve(&Board::new(4), Tile::Black).unwrap()), ["a4", "b3", "c2", "d1"]);
+
+        // The game's already over
+        let solution = solver.solve(&Board::from("B..|B..|B..|"), Tile::White).unwrap();
+        assert_eq!(solution, Solution { winner: Tile::Black, winning_moves: Vec::new() });
+    }
+
+    #[test]
+    fn agrees_with_brute_force() {
+        let positions = ["...|...|...|", "...|.W.|...|", "B..|.W.|...|", "..B|...|W..|", ".W.|B..|..B|",
+            "B..W|.W..|..B.|W.B.|", ".W..|B.B.|.B.W|W..W|", "..W.|.BB.|.W..|B.W.|"];
+
+        for position in positions {
+            let board = Board::from(position);
+            let to_move = side_to_move(&board);
+            let solution = Solver::new().solve(&board, to_move).unwrap();
+            assert_eq!(solution.winner, brute_force(&board, to_move), "{}", position);
+
+            for coord in empty_tiles(&board) {
+                let wins = brute_force(&play(&board, coord, to_move), to_move.opponent()) == to_move;
+                assert_eq!(solution.winning_moves.contains(&coord), wins, "{} {}", position, coord);
+            }
+        }
+    }
+
+    #[test]
+    fn gives_up_after_max_nodes() {
+        let mut solver = Solver::new();
+        solver.max_nodes = Some(10);
+        assert_eq!(solver.winner(&Board::new(6), Tile::Black), None);
+        assert!(solver.nodes <= 11);
+
+        assert_eq!(side_to_move(&Board::from("B..|...|...|")), Tile::White);
+        assert_eq!(side_to_move(&Board::from("B..|..W|...|")), Tile::Black);
+    }
+}
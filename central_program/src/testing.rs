@@ -2,6 +2,8 @@ use std::io::{BufRead, BufReader, Write};
 use std::process::{Child, Command, Stdio};
 use std::path::PathBuf;
 use termion::{color, style};
+use sentience_validator::board::{Coord, MAX_SIZE};
+use sentience_validator::Message;
 
 use super::Color;
 
@@ -103,20 +105,20 @@ impl BotTest {
         let mut reader = BufReader::new(bot_out);
         let mut real_out = String::new();
 
-        write!(bot_in, "init_board 4\n")
+        writeln!(bot_in, "{}", Message::InitBoard(4))
             .expect("Unset tiles: Failed to init board");
 
-        for coord in ["a1", "a2", "d3", "d4"] {
-            write!(bot_in, "sety {}\n", coord)
+        for tile in ["a1", "a2", "d3", "d4"] {
+            writeln!(bot_in, "{}", Message::SetY(coord(tile)))
                 .expect("Unset tiles: Failed to sety");
         }
 
-        for coord in ["d1", "d2", "a3", "a4"] {
-            write!(bot_in, "seto {}\n", coord)
+        for tile in ["d1", "d2", "a3", "a4"] {
+            writeln!(bot_in, "{}", Message::SetO(coord(tile)))
                 .expect("Unset tiles: Failed to seto");
         }
 
-        write!(bot_in, "unset a2\nshow_board\n")
+        send_all(bot_in, &[unset("a2"), Message::ShowBoard])
             .expect("Unset tiles: Failed to show_board");
 
         reader.read_line(&mut real_out).expect("Unset tiles: Failed to output");
@@ -126,7 +128,7 @@ impl BotTest {
             &real_out);
         real_out.clear();
 
-        write!(bot_in, "unset a3\nunset d2\nunset d3\nshow_board\n")
+        send_all(bot_in, &[unset("a3"), unset("d2"), unset("d3"), Message::ShowBoard])
             .expect("Unset tiles: Failed to unset");
 
         reader.read_line(&mut real_out).expect("Unset tiles: Failed to output");
@@ -136,7 +138,7 @@ impl BotTest {
             , &real_out);
         real_out.clear();
 
-        write!(bot_in, "unset a1\nunset a4\nunset d1\nunset d4\nshow_board\n")
+        send_all(bot_in, &[unset("a1"), unset("a4"), unset("d1"), unset("d4"), Message::ShowBoard])
             .expect("Unset tiles: Failed to unset");
 
         reader.read_line(&mut real_out).expect("Unset tiles: Failed to output");
@@ -144,7 +146,7 @@ impl BotTest {
         pretty_print_result("Unset all tiles", "....|....|....|....|\n", &real_out);
         real_out.clear();
 
-        write!(bot_in, "unset a1\nunset a4\nunset d1\nunset d4\nshow_board\n")
+        send_all(bot_in, &[unset("a1"), unset("a4"), unset("d1"), unset("d4"), Message::ShowBoard])
             .expect("Unset tiles: Failed to unset");
 
         reader.read_line(&mut real_out).expect("Unset tiles: Failed to output");
@@ -152,16 +154,36 @@ impl BotTest {
         pretty_print_result("Unset on empty tiles", "....|....|....|....|\n", &real_out);
         real_out.clear();
 
-        write!(bot_in, "sety a1\nsety a4\nsety d1\nsety d4\ninit_board 4\nshow_board\n")
+        let sety = |tile| Message::SetY(coord(tile));
+        send_all(bot_in, &[sety("a1"), sety("a4"), sety("d1"), sety("d4"), Message::InitBoard(4), Message::ShowBoard])
             .expect("Unset tiles: Failed init board");
 
         reader.read_line(&mut real_out).expect("Unset tiles: Failed to output");
 
         pretty_print_result("init_board unsets all tiles", "....|....|....|....|\n", &real_out);
 
+        let _ = bot.kill();
+        let _ = bot.wait();
     }
 }
 
+// Test tiles are always on the board
+fn coord(tile: &str) -> Coord {
+    Coord::parse(tile, MAX_SIZE).expect("Invalid test tile")
+}
+
+fn unset(tile: &str) -> Message {
+    Message::Unset(coord(tile))
+}
+
+// Writes each message on its own line
+fn send_all(bot_in: &mut impl Write, messages: &[Message]) -> std::io::Result<()> {
+    for message in messages {
+        writeln!(bot_in, "{}", message)?;
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 struct Test<T, G>
 where
@@ -183,7 +205,7 @@ where
     T: AsRef<str> + std::fmt::Display + std::fmt::Debug,
     G: AsRef<str> + std::fmt::Display + std::fmt::Debug
 {
-    pub fn run(mut self, cmd: &str) {
+    pub fn run(mut self, cmd: Message) {
         self.setup_board();
         self.real_out = self.get_out(cmd);
         self.pretty_print();
@@ -198,39 +220,39 @@ where
     fn setup_board(&mut self) {
         let bot_in = self.bot.stdin.as_mut().unwrap();
 
-        write!(bot_in, "init_board {}\n", self.board_size).expect("Failed to write init_board");
+        writeln!(bot_in, "{}", Message::InitBoard(self.board_size)).expect("Failed to write init_board");
 
-        let mut moves: Vec<String> = Vec::new();
+        let mut moves: Vec<Message> = Vec::new();
         let mut yi = 0;
         let mut oi = 0;
 
         // Interleave sety & seto moves to mimic game
         while yi < self.sety.len() || oi < self.seto.len() {
             if yi < self.sety.len() {
-                moves.push(format!("sety {}", self.sety[yi]));
+                moves.push(Message::SetY(coord(self.sety[yi].as_ref())));
                 yi += 1;
             }
             if oi < self.seto.len() {
-                moves.push(format!("seto {}", self.seto[oi]));
+                moves.push(Message::SetO(coord(self.seto[oi].as_ref())));
                 oi += 1;
             }
         }
 
         // Insert a swap move if required
-        if (self.swap >= 0) {
-            moves.insert(self.swap as usize, String::from("swap"));
+        if self.swap >= 0 {
+            moves.insert(self.swap as usize, Message::Swap);
         }
         
         for mv in moves.iter() {
-            write!(bot_in, "{}\n", mv).expect(&format!("Failed to write move {}", mv.as_str()));
+            writeln!(bot_in, "{}", mv).unwrap_or_else(|e| panic!("Failed to write move {}: {}", mv, e));
         }
     }
 
     // Check output
-    fn get_out(&mut self, cmd: &str) -> String {
+    fn get_out(&mut self, cmd: Message) -> String {
         let bot_out = self.bot.stdout.as_mut().unwrap();
         let bot_in = self.bot.stdin.as_mut().unwrap();
-        write!(bot_in, "{}\n", cmd).expect(&format!("Failed to write: {}", cmd));
+        writeln!(bot_in, "{}", cmd).unwrap_or_else(|e| panic!("Failed to write {}: {}", cmd, e));
         
         let mut reader = BufReader::new(bot_out);
         let mut output = String::new();
@@ -271,7 +293,7 @@ fn pretty_print_result(name: &str, expected_out: &str, real_out: &str) {
 fn test_init_board(bot: Child, size: u8) {
     let mut test = Test::<String, String> {
         name: format!("Creates a {}x{} board", size, size),
-        bot: bot,
+        bot,
         board_size: size,
         sety: vec![],
         seto: vec![],
@@ -282,13 +304,13 @@ fn test_init_board(bot: Child, size: u8) {
 
     for _row in 1..=size {
         for _column in 1..=size {
-            test.expected_out.push_str(".");
+            test.expected_out.push('.');
         }
-        test.expected_out.push_str("|");
+        test.expected_out.push('|');
     }
-    test.expected_out.push_str("\n");
+    test.expected_out.push('\n');
 
-    test.run("show_board")
+    test.run(Message::ShowBoard)
 }
 
 // Set your tile ====================================================
@@ -303,7 +325,7 @@ fn test_init_board(bot: Child, size: u8) {
 fn test_set_yours_a1(bot: Child, c: &str) {
     let test = Test {
         name: "Sets own tile on a1",
-        bot: bot,
+        bot,
         board_size: 3,
         sety: vec!["a1"],
         seto: vec![],
@@ -312,13 +334,13 @@ fn test_set_yours_a1(bot: Child, c: &str) {
         real_out: String::new(),
     };
 
-    test.run("show_board")
+    test.run(Message::ShowBoard)
 }
 
 fn test_set_yours_c8(bot: Child, c: &str) {
     let test = Test {
         name: "Sets own tile on c8",
-        bot: bot,
+        bot,
         board_size: 10,
         sety: vec!["c8"],
         seto: vec![],
@@ -337,13 +359,13 @@ fn test_set_yours_c8(bot: Child, c: &str) {
         real_out: String::new(),
     };
 
-    test.run("show_board")
+    test.run(Message::ShowBoard)
 }
 
 fn test_set_yours_all_rows(bot: Child, c: &str) {
     let test = Test {
         name: "Sets own tiles on first column",
-        bot: bot,
+        bot,
         board_size: 3,
         sety: vec!["a1", "b1", "c1"],
         seto: vec![],
@@ -352,13 +374,13 @@ fn test_set_yours_all_rows(bot: Child, c: &str) {
         real_out: String::new(),
     };
 
-    test.run("show_board")
+    test.run(Message::ShowBoard)
 }
 
 fn test_set_yours_fill(bot: Child, c: &str) {
     let test = Test {
         name: "Sets own tiles on every spot",
-        bot: bot,
+        bot,
         board_size: 3,
         sety: vec!["a1", "a2", "a3", "b1", "b2", "b3", "c1", "c2", "c3"],
         seto: vec![],
@@ -367,13 +389,13 @@ fn test_set_yours_fill(bot: Child, c: &str) {
         real_out: String::new(),
     };
 
-    test.run("show_board")
+    test.run(Message::ShowBoard)
 }
 
 fn test_set_yours_diagonal(bot: Child, c: &str) {
     let test = Test {
         name: "Sets own tiles diagonally",
-        bot: bot,
+        bot,
         board_size: 3,
         sety: vec!["a1", "b2", "c3"],
         seto: vec![],
@@ -382,13 +404,13 @@ fn test_set_yours_diagonal(bot: Child, c: &str) {
         real_out: String::new(),
     };
 
-    test.run("show_board")
+    test.run(Message::ShowBoard)
 }
 
 fn test_set_yours_twice_on_same_spot(bot: Child, c: &str) {
     let test = Test {
         name: "Sets own twice on same spot",
-        bot: bot,
+        bot,
         board_size: 3,
         sety: vec!["a1", "a1", "c3"],
         seto: vec![],
@@ -397,13 +419,13 @@ fn test_set_yours_twice_on_same_spot(bot: Child, c: &str) {
         real_out: String::new(),
     };
 
-    test.run("show_board")
+    test.run(Message::ShowBoard)
 }
 
 fn test_set_yours_big_diagonal(bot: Child, c: &str) {
     let test = Test {
         name: "Sets own tiles diagonally on a large board",
-        bot: bot,
+        bot,
         board_size: 12,
         sety: vec!["a1", "b2", "c3", "d4", "e5", "f6", "g7", "h8", "i9", "j10", "k11", "l12"],
         seto: vec![],
@@ -424,13 +446,13 @@ fn test_set_yours_big_diagonal(bot: Child, c: &str) {
         real_out: String::new(),
     };
 
-    test.run("show_board")
+    test.run(Message::ShowBoard)
 }
 
 fn test_set_yours_big_fill(bot: Child, c: &str) {
     let mut test = Test::<String, &str> {
         name: "Sets own tiles on every spot on a large board",
-        bot: bot,
+        bot,
         board_size: 12,
         sety: vec![],
         seto: vec![],
@@ -445,7 +467,7 @@ fn test_set_yours_big_fill(bot: Child, c: &str) {
         }
     }
 
-    test.run("show_board");
+    test.run(Message::ShowBoard);
 }
 
 // Set other player's tile ==========================================
@@ -455,7 +477,7 @@ fn test_set_yours_big_fill(bot: Child, c: &str) {
 fn test_set_others_a1(bot: Child, c: &str) {
     let test = Test {
         name: "Sets own tile on a1",
-        bot: bot,
+        bot,
         board_size: 3,
         sety: vec![],
         seto: vec!["a1"],
@@ -464,13 +486,13 @@ fn test_set_others_a1(bot: Child, c: &str) {
         real_out: String::new(),
     };
 
-    test.run("show_board")
+    test.run(Message::ShowBoard)
 }
 
 fn test_set_others_c8(bot: Child, c: &str) {
     let test = Test {
         name: "Sets own tile on c8",
-        bot: bot,
+        bot,
         board_size: 10,
         sety: vec![],
         seto: vec!["c8"],
@@ -489,13 +511,13 @@ fn test_set_others_c8(bot: Child, c: &str) {
         real_out: String::new(),
     };
 
-    test.run("show_board")
+    test.run(Message::ShowBoard)
 }
 
 fn test_set_others_all_rows(bot: Child, c: &str) {
     let test = Test {
         name: "Sets own tiles on first column",
-        bot: bot,
+        bot,
         board_size: 3,
         sety: vec![],
         seto: vec!["a1", "b1", "c1"],
@@ -504,13 +526,13 @@ fn test_set_others_all_rows(bot: Child, c: &str) {
         real_out: String::new(),
     };
 
-    test.run("show_board")
+    test.run(Message::ShowBoard)
 }
 
 fn test_set_others_fill(bot: Child, c: &str) {
     let test = Test {
         name: "Sets own tiles on every spot",
-        bot: bot,
+        bot,
         board_size: 3,
         sety: vec![],
         seto: vec!["a1", "a2", "a3", "b1", "b2", "b3", "c1", "c2", "c3"],
@@ -519,13 +541,13 @@ fn test_set_others_fill(bot: Child, c: &str) {
         real_out: String::new(),
     };
 
-    test.run("show_board")
+    test.run(Message::ShowBoard)
 }
 
 fn test_set_others_diagonal(bot: Child, c: &str) {
     let test = Test {
         name: "Sets own tiles diagonally",
-        bot: bot,
+        bot,
         board_size: 3,
         sety: vec![],
         seto: vec!["a1", "b2", "c3"],
@@ -534,13 +556,13 @@ fn test_set_others_diagonal(bot: Child, c: &str) {
         real_out: String::new(),
     };
 
-    test.run("show_board")
+    test.run(Message::ShowBoard)
 }
 
 fn test_set_others_twice_on_same_spot(bot: Child, c: &str) {
     let test = Test {
         name: "Sets own twice on same spot",
-        bot: bot,
+        bot,
         board_size: 3,
         sety: vec![],
         seto: vec!["a1", "a1", "c3"],
@@ -549,13 +571,13 @@ fn test_set_others_twice_on_same_spot(bot: Child, c: &str) {
         real_out: String::new(),
     };
 
-    test.run("show_board")
+    test.run(Message::ShowBoard)
 }
 
 fn test_set_others_big_diagonal(bot: Child, c: &str) {
     let test = Test {
         name: "Sets own tiles diagonally on a large board",
-        bot: bot,
+        bot,
         board_size: 12,
         sety: vec![],
         seto: vec!["a1", "b2", "c3", "d4", "e5", "f6", "g7", "h8", "i9", "j10", "k11", "l12"],
@@ -576,13 +598,13 @@ fn test_set_others_big_diagonal(bot: Child, c: &str) {
         real_out: String::new(),
     };
 
-    test.run("show_board")
+    test.run(Message::ShowBoard)
 }
 
 fn test_set_others_big_fill(bot: Child, c: &str) {
     let mut test = Test::<String, &str> {
         name: "Sets own tiles on every spot on a large board",
-        bot: bot,
+        bot,
         board_size: 12,
         sety: vec![],
         seto: vec![],
@@ -597,7 +619,7 @@ fn test_set_others_big_fill(bot: Child, c: &str) {
         }
     }
 
-    test.run("show_board");
+    test.run(Message::ShowBoard);
 }
 
 // Check for identifying win ========================================
@@ -610,7 +632,7 @@ fn test_set_others_big_fill(bot: Child, c: &str) {
 fn test_no_win_blank(bot: Child, size: u8) {
     let test = Test::<String, String> {
         name: format!("No win on blank {}x{} board", size, size),
-        bot: bot,
+        bot,
         board_size: size,
         sety: vec![],
         seto: vec![],
@@ -619,13 +641,13 @@ fn test_no_win_blank(bot: Child, size: u8) {
         real_out: String::new(),
     };
 
-    test.run("check_win");
+    test.run(Message::CheckWin);
 }
 
 fn test_white_win_small(bot: Child, is_white: bool) {
     let mut test = Test {
         name: "Identifies white win across one (top) row",
-        bot: bot,
+        bot,
         board_size: 3,
         sety: vec!["a1", "a2", "a3"],
         seto: vec![],
@@ -636,13 +658,13 @@ fn test_white_win_small(bot: Child, is_white: bool) {
 
     if !is_white { std::mem::swap(&mut test.sety, &mut test.seto) }
 
-    test.run("check_win");
+    test.run(Message::CheckWin);
 }
 
 fn test_black_win_small(bot: Child, is_white: bool) {
     let mut test = Test {
         name: "Identifies white win across one (left) column",
-        bot: bot,
+        bot,
         board_size: 3,
         sety: vec!["a1", "b1", "c1"],
         seto: vec![],
@@ -653,13 +675,13 @@ fn test_black_win_small(bot: Child, is_white: bool) {
 
     if is_white { std::mem::swap(&mut test.sety, &mut test.seto) }
 
-    test.run("check_win");
+    test.run(Message::CheckWin);
 }
 
 fn test_black_win_big(bot: Child, is_white: bool) {
     let mut test = Test {
         name: "Identifies black win on a big board",
-        bot: bot,
+        bot,
         board_size: 11,
         sety: vec!["a3", "b3", "c3", "c4", "b5", "b6", "b7", "b8", "b9", "b10", "b11", "c11",
             "d11", "e11", "f10", "g9", "h9", "i8", "j8", "j9", "j10", "j11", "k11"],
@@ -671,7 +693,7 @@ fn test_black_win_big(bot: Child, is_white: bool) {
 
     if is_white { std::mem::swap(&mut test.sety, &mut test.seto) }
 
-    test.run("check_win");
+    test.run(Message::CheckWin);
 }
 
 fn test_swap_move_first(bot: Child, is_white: bool) {
@@ -679,7 +701,7 @@ fn test_swap_move_first(bot: Child, is_white: bool) {
     let black = "B";
     let mut test = Test {
         name: "Tests swap move sety",
-        bot: bot,
+        bot,
         board_size: 10,
         sety: vec!["i2", "a3"],
         seto: vec![],
@@ -700,7 +722,7 @@ fn test_swap_move_first(bot: Child, is_white: bool) {
 
     if is_white { std::mem::swap(&mut test.sety, &mut test.seto) };
 
-    test.run("show_board");
+    test.run(Message::ShowBoard);
 }
 
 fn test_swap_move_second(bot: Child, is_white: bool) {
@@ -708,7 +730,7 @@ fn test_swap_move_second(bot: Child, is_white: bool) {
     let black = "B";
     let mut test = Test {
         name: "Tests swap move seto",
-        bot: bot,
+        bot,
         board_size: 10,
         sety: vec![],
         seto: vec!["i2", "a3"],
@@ -729,7 +751,7 @@ fn test_swap_move_second(bot: Child, is_white: bool) {
 
     if is_white { std::mem::swap(&mut test.sety, &mut test.seto) };
 
-    test.run("show_board");
+    test.run(Message::ShowBoard);
 }
 
 // TODO: Test "swap" command
@@ -0,0 +1,515 @@
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::game::{Game, GameResult, Referee, TimeControl};
+use crate::board::Tile;
+use crate::ratings::{self, Sprt, SprtDecision};
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Format {
+    /// Every pair of bots plays once, alternating colors between games
+    RoundRobin,
+    /// Every pair of bots plays once with each color
+    DoubleRoundRobin,
+    /// Bots with similar scores are paired up each round, avoiding rematches
+    Swiss,
+}
+
+// A finished game. Bots are referred to by their index in the tournament's bot list
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub round: usize,
+    pub game: usize,
+    pub black: usize,
+    pub white: usize,
+    pub winner: Tile,
+    pub summary: String,
+}
+
+impl Record {
+    fn winner_index(&self) -> Option<usize> {
+        match self.winner {
+            Tile::Black => Some(self.black),
+            Tile::White => Some(self.white),
+            Tile::Empty => None,
+        }
+    }
+
+    // The winner and loser, unless neither bot could be started
+    pub fn outcome(&self) -> Option<(usize, usize)> {
+        match self.winner {
+            Tile::Black => Some((self.black, self.white)),
+            Tile::White => Some((self.white, self.black)),
+            Tile::Empty => None,
+        }
+    }
+}
+
+// Reads the bots and finished games from a tournament's results file
+pub fn read_results(path: &Path) -> io::Result<(Vec<PathBuf>, Vec<Record>)> {
+    let contents = fs::read_to_string(path)?;
+    parse_results(path, &contents).map(|(bots, records, _)| (bots, records))
+}
+
+// Also returns the length of the complete lines. A tournament interrupted while saving a game can
+// leave half of its line at the end of the file, which is left out so that the game is played again
+fn parse_results(path: &Path, contents: &str) -> io::Result<(Vec<PathBuf>, Vec<Record>, usize)> {
+    let bots: Vec<PathBuf> = contents.lines()
+        .filter_map(|line| line.strip_prefix("# bot "))
+        .filter_map(|line| line.split_once(": ").map(|(_, bot)| PathBuf::from(bot)))
+        .collect();
+
+    let mut records = Vec::new();
+    let mut end = 0;
+    for (i, line) in contents.split_inclusive('\n').enumerate() {
+        let start = end;
+        end += line.len();
+        let line = line.trim_end_matches(['\n', '\r']);
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+
+        match parse_record(line, bots.len()) {
+            Some(record) => records.push(record),
+            None if end == contents.len() && !contents.ends_with('\n') => {
+                eprintln!("Ignoring the unfinished last line of {}: `{}`", path.display(), line);
+                return Ok((bots, records, start));
+            }
+            None => return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{} isn't a game from this tournament: `{}`", path.display(), i + 1, line),
+            )),
+        }
+    }
+    Ok((bots, records, end))
+}
+
+// Bots are numbered from 1, as in the file's header. Ex:
+// "1\t2\t1\t3\twinner=black reason=connection moves=31"
+fn parse_record(line: &str, bots: usize) -> Option<Record> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() != 5 {
+        return None;
+    }
+
+    let index_of = |bot: &str| bot.parse::<usize>().ok()
+        .filter(|&bot| 1 <= bot && bot <= bots)
+        .map(|bot| bot - 1);
+    let winner = match fields[4].split(' ').find_map(|kv| kv.strip_prefix("winner="))? {
+        "black" => Tile::Black,
+        "white" => Tile::White,
+        "none" => Tile::Empty,
+        _ => return None,
+    };
+
+    Some(Record {
+        round: fields[0].parse().ok()?,
+        game: fields[1].parse().ok()?,
+        black: index_of(fields[2])?,
+        white: index_of(fields[3])?,
+        winner,
+        summary: fields[4].to_string(),
+    })
+}
+
+// Ex: "2:main.py" for ../random_bot/main.py, the second bot in the tournament
+pub fn bot_name(bots: &[PathBuf], bot: usize) -> String {
+    let path = &bots[bot];
+    let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+    format!("{}:{}", bot + 1, name)
+}
+
+// Starts both bots and plays a whole game between them, without printing the moves
+pub fn play_game(size: u8, black: &Path, white: &Path, time: &TimeControl, referee: &Referee)
+    -> GameResult
+{
+    match Game::start(size, black, white, time.clone(), referee.clone()) {
+        Ok(mut game) => {
+            game.verbose = false;
+            let mut result = game.play_out();
+            result.usage = game.shutdown();
+            result
+        }
+        Err(result) => *result,
+    }
+}
+
+// Plays a new version of a bot against an old one, alternating colors, until the test reaches a
+// decision or `max_games` have been played. Games that no bot could start don't count
+pub fn run_sprt(size: u8, new_bot: &Path, old_bot: &Path, sprt: Sprt, max_games: usize,
+    time: &TimeControl, referee: &Referee) -> (SprtDecision, usize, usize)
+{
+    let (mut wins, mut losses) = (0, 0);
+
+    for game in 1..=max_games {
+        let (black, white, new_color) = if game % 2 == 1 {
+            (new_bot, old_bot, Tile::Black)
+        } else {
+            (old_bot, new_bot, Tile::White)
+        };
+        let result = play_game(size, black, white, time, referee);
+
+        if result.winner == new_color {
+            wins += 1;
+        } else if result.winner != Tile::Empty {
+            losses += 1;
+        }
+
+        let (lower, upper) = sprt.bounds();
+        println!("Game {}: new bot plays {}: {}. Score {}-{}, LLR {:.3} ({:.3}, {:.3})", game,
+            new_color.to_string().to_lowercase(), result, wins, losses, sprt.llr(wins, losses),
+            lower, upper);
+        if let Some(details) = result.reason.details() {
+            println!("  {}", details);
+        }
+
+        match sprt.decide(wins, losses) {
+            SprtDecision::Continue => (),
+            decision => return (decision, wins, losses),
+        }
+    }
+    (SprtDecision::Continue, wins, losses)
+}
+
+
+// A game that still has to be played
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Pairing {
+    round: usize,
+    game: usize,
+    black: usize,
+    white: usize,
+}
+
+pub struct Tournament {
+    bots: Vec<PathBuf>,
+    size: u8,
+    format: Format,
+    rounds: usize,
+    games: usize,
+    time: TimeControl,
+    results_file: Option<PathBuf>,
+    records: Vec<Record>,
+    // Recording and strict checks for every game
+    pub referee: Referee,
+}
+
+impl Tournament {
+    pub fn new(bots: Vec<PathBuf>, size: u8, format: Format, rounds: Option<usize>, games: usize,
+        time: TimeControl) -> Self
+    {
+        // Enough rounds for a single bot to beat everyone in a knockout
+        let swiss_rounds = (usize::BITS - (bots.len().max(2) - 1).leading_zeros()) as usize;
+
+        Self {
+            rounds: match format {
+                Format::Swiss => rounds.unwrap_or(swiss_rounds),
+                _ => rounds.unwrap_or(1),
+            },
+            bots,
+            size,
+            format,
+            games,
+            time,
+            results_file: None,
+            records: Vec::new(),
+            referee: Referee::default(),
+        }
+    }
+
+    // Stores every finished game in the given file. Games already in there are skipped, so an
+    // interrupted tournament can be resumed by running the same command again
+    pub fn resume_from(&mut self, path: PathBuf) -> io::Result<()> {
+        if path.exists() {
+            let contents = fs::read_to_string(&path)?;
+            let (bots, records, end) = parse_results(&path, &contents)?;
+
+            if bots != self.bots {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("{} was written by a tournament with different bots", path.display())));
+            }
+            self.records = records;
+
+            // New games go on a line of their own, after any unfinished one is cut off
+            let mut file = OpenOptions::new().append(true).open(&path)?;
+            file.set_len(end as u64)?;
+            if !contents[..end].is_empty() && !contents[..end].ends_with('\n') {
+                writeln!(file)?;
+            }
+        }
+        self.results_file = Some(path);
+        Ok(())
+    }
+
+    pub fn run(&mut self) -> io::Result<()> {
+        for round in 1..=self.rounds {
+            let pairings = self.schedule(round);
+
+            for pairing in pairings {
+                if self.records.iter().any(|r| r.round == pairing.round && r.game == pairing.game
+                    && r.black == pairing.black && r.white == pairing.white)
+                {
+                    continue;
+                }
+
+                let result = self.play(pairing);
+                let record = Record {
+                    round: pairing.round,
+                    game: pairing.game,
+                    black: pairing.black,
+                    white: pairing.white,
+                    winner: result.winner,
+                    summary: result.to_string(),
+                };
+
+                println!("Round {} game {}: {} (black) vs {} (white): {}", record.round, record.game,
+                    bot_name(&self.bots, record.black), bot_name(&self.bots, record.white),
+                    record.summary);
+                if let Some(details) = result.reason.details() {
+                    println!("  {}", details);
+                }
+                self.save(&record)?;
+                self.records.push(record);
+            }
+        }
+        Ok(())
+    }
+
+    fn play(&self, pairing: Pairing) -> GameResult {
+        play_game(self.size, &self.bots[pairing.black], &self.bots[pairing.white], &self.time,
+            &self.referee)
+    }
+
+    fn save(&self, record: &Record) -> io::Result<()> {
+        let path = match &self.results_file {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let is_new = !path.exists();
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        if is_new {
+            for (i, bot) in self.bots.iter().enumerate() {
+                writeln!(file, "# bot {}: {}", i + 1, bot.display())?;
+            }
+            writeln!(file, "# round\tgame\tblack\twhite\tresult")?;
+        }
+        writeln!(file, "{}\t{}\t{}\t{}\t{}", record.round, record.game, record.black + 1,
+            record.white + 1, record.summary)
+    }
+
+    // All games to be played in a round. Swiss pairings depend on the results of earlier rounds.
+    // Round robins play the same pairings every round, with the colors of a single round robin
+    // turned around every other round
+    fn schedule(&self, round: usize) -> Vec<Pairing> {
+        let matchups = match self.format {
+            Format::RoundRobin | Format::DoubleRoundRobin => {
+                let n = self.bots.len();
+                (0..n).flat_map(|a| (a + 1..n).map(move |b| (a, b))).collect()
+            }
+            Format::Swiss => self.swiss_matchups(round),
+        };
+
+        let mut pairings = Vec::new();
+        for (a, b) in matchups {
+            for game in 1..=self.games {
+                if self.format == Format::DoubleRoundRobin {
+                    pairings.push(Pairing { round, game, black: a, white: b });
+                    pairings.push(Pairing { round, game, black: b, white: a });
+                } else if (game + round).is_multiple_of(2) {
+                    pairings.push(Pairing { round, game, black: a, white: b });
+                } else {
+                    pairings.push(Pairing { round, game, black: b, white: a });
+                }
+            }
+        }
+        pairings
+    }
+
+    // Pairs the highest ranked unpaired bot with the next best one it hasn't met yet. With an odd
+    // number of bots, the lowest ranked bot that hasn't had a bye sits out and scores a point
+    fn swiss_matchups(&self, round: usize) -> Vec<(usize, usize)> {
+        let mut scores = vec![0.0; self.bots.len()];
+        let mut byes = HashSet::new();
+        let mut met = HashSet::new();
+
+        for earlier in 1..round {
+            if let Some(bye) = self.swiss_bye(&scores, &byes) {
+                scores[bye] += 1.0;
+                byes.insert(bye);
+            }
+
+            for record in self.records.iter().filter(|r| r.round == earlier) {
+                met.insert((record.black.min(record.white), record.black.max(record.white)));
+
+                if let Some(winner) = record.winner_index() {
+                    scores[winner] += 1.0 / self.games as f64;
+                }
+            }
+        }
+
+        let bye = self.swiss_bye(&scores, &byes);
+        let mut unpaired = self.ranking(&scores);
+        unpaired.retain(|&bot| Some(bot) != bye);
+
+        // Once everyone has met, rematches are unavoidable. Pair neighbours in the ranking then
+        pair_without_rematches(&unpaired, &met)
+            .unwrap_or_else(|| unpaired.chunks_exact(2).map(|pair| (pair[0], pair[1])).collect())
+    }
+
+    fn swiss_bye(&self, scores: &[f64], byes: &HashSet<usize>) -> Option<usize> {
+        if self.bots.len().is_multiple_of(2) {
+            return None;
+        }
+        self.ranking(scores).into_iter().rev().find(|bot| !byes.contains(bot))
+    }
+
+    // Bot indices from highest to lowest score. Ties keep the order the bots were given in
+    fn ranking(&self, scores: &[f64]) -> Vec<usize> {
+        let mut ranking: Vec<usize> = (0..self.bots.len()).collect();
+        ranking.sort_by(|&a, &b| scores[b].partial_cmp(&scores[a]).unwrap().then(a.cmp(&b)));
+        ranking
+    }
+
+    pub fn print_report(&self) {
+        let names: Vec<String> = (0..self.bots.len()).map(|bot| bot_name(&self.bots, bot)).collect();
+        let games: Vec<(usize, usize)> = self.records.iter().filter_map(Record::outcome).collect();
+        ratings::print_report(&names, &games);
+    }
+}
+
+// Pairs the highest ranked bot with the best opponent it hasn't met yet, backtracking whenever that
+// leaves the bots further down with nobody new to play
+fn pair_without_rematches(ranked: &[usize], met: &HashSet<(usize, usize)>)
+    -> Option<Vec<(usize, usize)>>
+{
+    let (&top, rest) = match ranked.split_first() {
+        Some(split) => split,
+        None => return Some(Vec::new()),
+    };
+
+    for (i, &other) in rest.iter().enumerate() {
+        if met.contains(&(top.min(other), top.max(other))) {
+            continue;
+        }
+
+        let mut remaining = rest.to_vec();
+        remaining.remove(i);
+
+        if let Some(mut pairs) = pair_without_rematches(&remaining, met) {
+            pairs.insert(0, (top, other));
+            return Some(pairs);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tournament_testing {
+    use super::*;
+    use std::time::Duration;
+
+    fn tournament(bots: usize, format: Format, games: usize) -> Tournament {
+        let time = TimeControl {
+            move_time: None,
+            game_time: None,
+            increment: Duration::ZERO,
+            send_time_left: false,
+        };
+        let bots = (0..bots).map(|i| PathBuf::from(format!("bot{}", i))).collect();
+        Tournament::new(bots, 11, format, None, games, time)
+    }
+
+    fn finish(tournament: &mut Tournament, round: usize, winner: Tile) {
+        for pairing in tournament.schedule(round) {
+            tournament.records.push(Record {
+                round,
+                game: pairing.game,
+                black: pairing.black,
+                white: pairing.white,
+                winner,
+                summary: String::new(),
+            });
+        }
+    }
+
+    #[test]
+    fn round_robin_schedules() {
+        let single = tournament(4, Format::RoundRobin, 3).schedule(1);
+        assert_eq!(single.len(), 6 * 3);
+        assert!(single.contains(&Pairing { round: 1, game: 1, black: 0, white: 3 }));
+        assert!(single.contains(&Pairing { round: 1, game: 2, black: 3, white: 0 }));
+        assert!(single.contains(&Pairing { round: 1, game: 3, black: 0, white: 3 }));
+
+        let double = tournament(4, Format::DoubleRoundRobin, 1).schedule(1);
+        assert_eq!(double.len(), 6 * 2);
+        assert!(double.contains(&Pairing { round: 1, game: 1, black: 1, white: 2 }));
+        assert!(double.contains(&Pairing { round: 1, game: 1, black: 2, white: 1 }));
+
+        let repeated = Tournament::new(vec![PathBuf::from("a"), PathBuf::from("b")], 11, Format::RoundRobin,
+            Some(2), 1, tournament(2, Format::RoundRobin, 1).time);
+        assert_eq!(repeated.rounds, 2);
+        assert_eq!(repeated.schedule(1), vec![Pairing { round: 1, game: 1, black: 0, white: 1 }]);
+        assert_eq!(repeated.schedule(2), vec![Pairing { round: 2, game: 1, black: 1, white: 0 }]);
+    }
+
+    #[test]
+    fn swiss_avoids_rematches_and_repeat_byes() {
+        let mut swiss = tournament(5, Format::Swiss, 1);
+        assert_eq!(swiss.rounds, 3);
+
+        let mut byes = HashSet::new();
+        let mut met = HashSet::new();
+
+        for round in 1..=swiss.rounds {
+            let pairings = swiss.schedule(round);
+            assert_eq!(pairings.len(), 2);
+
+            let mut playing: Vec<usize> = pairings.iter().flat_map(|p| [p.black, p.white]).collect();
+            playing.sort();
+            playing.dedup();
+            assert_eq!(playing.len(), 4);
+
+            let bye = (0..5).find(|bot| !playing.contains(bot)).unwrap();
+            assert!(byes.insert(bye));
+
+            for p in &pairings {
+                assert!(met.insert((p.black.min(p.white), p.black.max(p.white))));
+            }
+            finish(&mut swiss, round, Tile::Black);
+        }
+    }
+
+    #[test]
+    fn results_round_trip() {
+        let record = parse_record("2\t1\t3\t1\twinner=white reason=timeout moves=7", 3).unwrap();
+
+        assert_eq!((record.round, record.game, record.black, record.white), (2, 1, 2, 0));
+        assert_eq!(record.outcome(), Some((0, 2)));
+        assert_eq!(parse_record("2\t1\t4\t1\twinner=white reason=timeout moves=7", 3), None);
+        assert_eq!(parse_record("2\t1\t3\t1\twinner=red", 3), None);
+    }
+
+    #[test]
+    fn resuming_cuts_off_an_unfinished_last_line() {
+        let path = std::env::temp_dir().join(format!("truncated_results_{}.tsv", std::process::id()));
+        let header = "# bot 1: bot0\n# bot 2: bot1\n# round\tgame\tblack\twhite\tresult\n";
+        let finished = "1\t1\t1\t2\twinner=black reason=connection moves=21\n";
+
+        fs::write(&path, format!("{}{}1\t2\t2\t1\twinner=wh", header, finished)).unwrap();
+        let (_, records) = read_results(&path).unwrap();
+        assert_eq!(records.len(), 1);
+
+        let mut resumed = tournament(2, Format::RoundRobin, 2);
+        resumed.resume_from(path.clone()).unwrap();
+        assert_eq!(resumed.records.len(), 1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("{}{}", header, finished));
+
+        // A bad line anywhere else is still an error
+        fs::write(&path, format!("{}1\t2\t2\t1\twinner=wh\n{}", header, finished)).unwrap();
+        assert!(read_results(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
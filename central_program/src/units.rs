@@ -0,0 +1,73 @@
+// Parsers for the durations and sizes given on the command line, shared by the referee's options
+// and the bots'
+use std::time::Duration;
+
+// Parses durations like "120s", "10m", "1.5h" or "500ms". Plain numbers are seconds
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let number = number.parse::<f64>()
+        .map_err(|_| format!("`{}` doesn't start with a number", s))?;
+
+    let seconds = match unit {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(format!("Unknown time unit `{}`. Use one of ms, s, m or h", unit)),
+    };
+    // Negative and NaN times can't come out of the digits above, but are turned down all the same
+    if seconds.is_nan() || seconds < 0.0 {
+        return Err(format!("`{}` isn't a length of time", s));
+    }
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("`{}` is too long", s))
+}
+
+// Parses sizes like "500k", "10M" or "1G", in bytes. Plain numbers are bytes
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let number = number.parse::<u64>()
+        .map_err(|_| format!("`{}` doesn't start with a whole number", s))?;
+
+    let scale = match unit {
+        "" | "B" => 1,
+        "k" | "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return Err(format!("Unknown size unit `{}`. Use one of k, M or G", unit)),
+    };
+    number.checked_mul(scale).ok_or_else(|| format!("`{}` is too large", s))
+}
+
+#[cfg(test)]
+mod units_testing {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("120s"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1.5m"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("2"), Ok(Duration::from_secs(2)));
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("3d").is_err());
+        assert!(parse_duration("-5s").is_err());
+        assert!(parse_duration("NaN").is_err());
+        assert_eq!(parse_duration("100000000000000000000000s"), Err("`100000000000000000000000s` is too long".to_string()));
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("500k"), Ok(500 << 10));
+        assert_eq!(parse_size("10M"), Ok(10 << 20));
+        assert_eq!(parse_size("1G"), Ok(1 << 30));
+        assert_eq!(parse_size("64"), Ok(64));
+        assert!(parse_size("1.5G").is_err());
+        assert!(parse_size("1T").is_err());
+        assert_eq!(parse_size("99999999999G"), Err("`99999999999G` is too large".to_string()));
+        assert_eq!(parse_size(&u64::MAX.to_string()), Ok(u64::MAX));
+    }
+}
@@ -0,0 +1,243 @@
+// Runs the built binaries against each other, with the Rust bots standing in for real ones
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::Command;
+
+const CONTROLLER: &str = env!("CARGO_BIN_EXE_central_controller");
+const RANDOM_BOT: &str = env!("CARGO_BIN_EXE_random_bot");
+const MCTS_BOT: &str = env!("CARGO_BIN_EXE_mcts_bot");
+
+// A misbehaving bot written as a shell script, in a file of its own
+fn script_bot(name: &str, body: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{}_{}.sh", name, std::process::id()));
+    std::fs::write(&path, format!("#!/bin/sh\n{}", body)).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[test]
+fn bots_pass_bot_test() {
+    for (bot, color) in [(RANDOM_BOT, "black"), (RANDOM_BOT, "white"), (MCTS_BOT, "black"), (MCTS_BOT, "white")] {
+        let output = Command::new(CONTROLLER).args(["test", bot, color]).output().unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("ok"), "{}", stdout);
+        assert!(!stdout.contains("FAILED"), "{}", stdout);
+    }
+}
+
+#[test]
+fn random_bots_play_a_game() {
+    let output = Command::new(CONTROLLER).args(["play", "5", RANDOM_BOT, RANDOM_BOT]).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let result = stdout.lines().last().unwrap();
+
+    assert!(result.starts_with("result winner="), "{}", stdout);
+    assert!(result.contains("reason=connection"), "{}", stdout);
+    assert!(matches!(output.status.code(), Some(0 | 1)));
+
+    // The winning chain reaches across the board, so there's a stone on every row or column of it
+    let path = result.split(' ').find_map(|field| field.strip_prefix("path=")).unwrap();
+    assert!(path.split(',').count() >= 5, "{}", stdout);
+    assert!(stdout.contains(&format!("Winning path: {}", path.replace(',', " "))), "{}", stdout);
+}
+
+#[test]
+fn board_sizes_out_of_range_are_rejected() {
+    for size in ["0", "27"] {
+        let output = Command::new(CONTROLLER).args(["play", size, RANDOM_BOT, RANDOM_BOT]).output().unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(stderr.contains("is not in 1..=26"), "{}", stderr);
+        assert!(output.stdout.is_empty());
+    }
+}
+
+#[test]
+fn strict_mode_catches_a_desync() {
+    // Answers as if nothing was ever played, and never gets as far as having to move
+    let script = script_bot("frozen_bot", "while read line; do\n  case \"$line\" in\n    \
+        show_board) echo '...|...|...|' ;;\n    check_win) echo 0 ;;\n    quit) exit 0 ;;\n  esac\ndone\n");
+
+    let record = std::env::temp_dir().join(format!("desync_{}.txt", std::process::id()));
+    let output = Command::new(CONTROLLER)
+        .args(["play", "3", RANDOM_BOT, script.to_str().unwrap(), "--strict-check-win"])
+        .args(["--record", record.to_str().unwrap()])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("White forfeits, out of sync with the central board: board differs at"), "{}", stdout);
+    assert!(stdout.contains("(central Black, bot Empty)"), "{}", stdout);
+    assert!(stdout.lines().last().unwrap().starts_with("result winner=black reason=desync moves=1 "), "{}", stdout);
+    assert_eq!(output.status.code(), Some(2));
+
+    // The game is recorded with the bot at fault resigning
+    let line = std::fs::read_to_string(&record).unwrap();
+    std::fs::remove_file(&record).unwrap();
+    assert!(line.ends_with(",w resign,B\n"), "{}", line);
+
+    // A win by connection stands, without checking boards that no longer matter
+    let output = Command::new(CONTROLLER)
+        .args(["play", "1", RANDOM_BOT, script.to_str().unwrap(), "--strict-check-win"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    std::fs::remove_file(&script).unwrap();
+    assert!(stdout.lines().last().unwrap().starts_with("result winner=black reason=connection moves=1 path=a1 "), "{}", stdout);
+
+    // Bots that keep up pass the same checks
+    let output = Command::new(CONTROLLER)
+        .args(["play", "5", RANDOM_BOT, MCTS_BOT, "--strict-check-win"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.lines().last().unwrap().contains("reason=connection"), "{}", stdout);
+}
+
+#[test]
+fn swaps_are_recorded_and_checked() {
+    // random_bot always swaps when it can, so every game has one
+    for rule in ["color", "stone"] {
+        let record = std::env::temp_dir().join(format!("swap_{}_{}.txt", rule, std::process::id()));
+
+        for _ in 0..5 {
+            let output = Command::new(CONTROLLER)
+                .args(["play", "4", RANDOM_BOT, RANDOM_BOT, "--strict-check-win", "--swap-rule", rule])
+                .args(["--record", record.to_str().unwrap()])
+                .output()
+                .unwrap();
+            let stdout = String::from_utf8_lossy(&output.stdout);
+
+            assert!(stdout.contains("White's move: swap"), "{}", stdout);
+            assert!(stdout.lines().last().unwrap().contains("reason=connection"), "{}", stdout);
+        }
+
+        let output = Command::new(CONTROLLER)
+            .args(["validate", record.to_str().unwrap(), "--size", "4"])
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        std::fs::remove_file(&record).unwrap();
+
+        assert!(stdout.contains("validate lines=5 valid=5"), "{}", stdout);
+    }
+}
+
+#[test]
+fn bad_bots_forfeit() {
+    let crashing = script_bot("crashing_bot", "read line\necho 'out of memory' >&2\nexit 3\n");
+    let chatty = script_bot("chatty_bot", "while read line; do\n  \
+        [ \"$line\" = make_move ] && echo 'thinking...'\n  [ \"$line\" = quit ] && exit 0\ndone\n");
+
+    for (bot, reason, message) in [
+        (&crashing, "crash", "White forfeits by crashing: exit status: 3, stderr: out of memory"),
+        (&chatty, "malformed_reply", "White forfeits with malformed reply `thinking...`"),
+    ] {
+        let output = Command::new(CONTROLLER).args(["play", "3", RANDOM_BOT, bot.to_str().unwrap()]).output().unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(stdout.contains(message), "{}", stdout);
+        let result = format!("result winner=black reason={} moves=2 ", reason);
+        assert!(stdout.lines().last().unwrap().starts_with(&result), "{}", stdout);
+    }
+
+    // The rest of the tournament goes ahead without them
+    let output = Command::new(CONTROLLER)
+        .args(["tournament", "3", RANDOM_BOT, crashing.to_str().unwrap(), RANDOM_BOT, chatty.to_str().unwrap()])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    std::fs::remove_file(&crashing).unwrap();
+    std::fs::remove_file(&chatty).unwrap();
+
+    assert!(output.status.success(), "{}", stdout);
+    assert_eq!(stdout.matches("reason=crash").count(), 3, "{}", stdout);
+    assert!(stdout.contains("  exit status: 3, stderr: out of memory"), "{}", stdout);
+    assert!(stdout.contains("reason=malformed_reply"), "{}", stdout);
+}
+
+#[test]
+fn stderr_goes_to_capped_logs() {
+    let dir = std::env::temp_dir().join(format!("stderr_logs_{}", std::process::id()));
+    let record = dir.join("games.txt");
+    let noisy = script_bot("noisy_bot", "read line\nhead -c 5000 /dev/zero | tr '\\0' x >&2\necho >&2\nexit 1\n");
+
+    let output = Command::new(CONTROLLER)
+        .args(["play", "3", RANDOM_BOT, noisy.to_str().unwrap(), "--stderr-cap", "1k"])
+        .args(["--stderr-dir", dir.to_str().unwrap(), "--record", record.to_str().unwrap()])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("random_bot seed"), "{}", stderr);
+
+    let line = std::fs::read_to_string(&record).unwrap();
+    let (game, comment) = line.trim_end().split_once(" # stderr black=").unwrap();
+    let (black, white) = comment.split_once(" white=").unwrap();
+
+    assert!(game.ends_with("w resign,B"), "{}", line);
+    assert!(std::fs::read_to_string(black).unwrap().starts_with("random_bot seed"));
+    let white = std::fs::read_to_string(white).unwrap();
+    assert!(white.starts_with(&"x".repeat(1024)), "{}", white);
+    assert!(white.ends_with("x\n[stderr cut off after 1024 bytes]\n"), "{}", white);
+
+    let output = Command::new(CONTROLLER).args(["validate", record.to_str().unwrap(), "--size", "3"]).output().unwrap();
+    assert!(output.status.success());
+
+    // Output that fills the cap exactly still gets the note once more follows
+    let exact = script_bot("exact_bot", "read line\nhead -c 1023 /dev/zero | tr '\\0' x >&2\necho >&2\necho more >&2\nexit 1\n");
+    let exact_dir = dir.join("exact");
+    Command::new(CONTROLLER)
+        .args(["play", "3", RANDOM_BOT, exact.to_str().unwrap(), "--stderr-cap", "1k"])
+        .args(["--stderr-dir", exact_dir.to_str().unwrap()])
+        .output()
+        .unwrap();
+
+    let log = std::fs::read_dir(&exact_dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.to_string_lossy().ends_with("-white.log"))
+        .unwrap();
+    let white = std::fs::read_to_string(log).unwrap();
+    assert_eq!(white, format!("{}\n\n[stderr cut off after 1024 bytes]\n", "x".repeat(1023)));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    std::fs::remove_file(&noisy).unwrap();
+    std::fs::remove_file(&exact).unwrap();
+}
+
+#[test]
+fn limits_are_applied_and_usage_reported() {
+    let output = Command::new(CONTROLLER)
+        .args(["play", "5", RANDOM_BOT, RANDOM_BOT, "--memory-limit", "1G", "--cpu-limit", "1m"])
+        .args(["--nice", "5", "--black-cpus", "0", "--white-cpus", "0"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let result = stdout.lines().last().unwrap();
+
+    assert!(result.contains("reason=connection"), "{}", stdout);
+    for field in ["black_cpu_ms=", "black_peak_rss_kb=", "white_cpu_ms=", "white_peak_rss_kb="] {
+        assert!(result.contains(field), "{}", stdout);
+    }
+
+    // A bot that spins forever runs out of CPU time instead of holding up the game
+    let spinning = script_bot("spinning_bot", "read line\nwhile :; do :; done\n");
+    let output = Command::new(CONTROLLER)
+        .args(["play", "3", RANDOM_BOT, spinning.to_str().unwrap(), "--cpu-limit", "1s"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    std::fs::remove_file(&spinning).unwrap();
+
+    assert!(stdout.contains("White forfeits by crashing: signal: 24 (SIGXCPU)"), "{}", stdout);
+    assert!(stdout.lines().last().unwrap().contains("reason=crash"), "{}", stdout);
+
+    // Limits that can't be applied keep the bot from starting
+    let output = Command::new(CONTROLLER)
+        .args(["play", "3", RANDOM_BOT, RANDOM_BOT, "--white-cpus", "100000"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().last().unwrap(), "result winner=black reason=error moves=0");
+}
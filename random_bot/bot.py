@@ -22,6 +22,7 @@ class RandomHexBot:
             "sety": self.sety,
             "unset": self.unset,
             "check_win": self.check_win,
+            "time_left": self.time_left,
         }
 
         self.argnums = {
@@ -32,6 +33,7 @@ class RandomHexBot:
             "sety": 1,
             "unset": 1,
             "check_win": 0,
+            "time_left": 1,
         }
 
     def is_cmd(self, cmd):
@@ -114,6 +116,14 @@ class RandomHexBot:
         print(move)
         return
 
+    def time_left(self, ms):
+        """Tells the bot how long it has for its next move. This bot is quick enough to ignore it
+
+        Args:
+            ms (str): Milliseconds left before the bot forfeits on time
+        """
+        return
+
     def swap(self):
         """
         Performs the 'swap' move
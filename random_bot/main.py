@@ -25,6 +25,7 @@ def main():
         ["sety {}", "sety a1", "Tells the bot to play a move for itself"],
         ["swap", "swap", "Uses the opening \"swap\" move in Hex"],
         ["unset {}", "unset a1", "Tells the bot to set a tile as unused"],
+        ["time_left {ms}", "time_left 1500", "Optional. Tells the bot how many milliseconds it has left for its next move"],
         ["check_win", "check_win", "Tells the bot to check if the game is over. Returns 1 if itself has won, -1 if the opponent has won, 0 if the game has not terminated"],
         ["quit", "quit", "The game is over"]
     ]